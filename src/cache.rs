@@ -0,0 +1,220 @@
+//! Persistent on-disk cache of full-file hashes.
+//!
+//! Entries are keyed by the raw bytes of the path and the hash algorithm and remember the file's size,
+//! modification time, change time and inode at the moment it was hashed. A cached
+//! hash is only reused while all of those still match, so edited, replaced or
+//! touched files are transparently rehashed.
+
+use crate::hashing::HashAlgorithm;
+use crate::paths::{path_from_bytes, path_to_bytes};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File, Metadata},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Bumped whenever the on-disk layout changes; caches with another version are discarded.
+const CACHE_VERSION: u32 = 2;
+
+/// File name of the cache inside the cache directory.
+const CACHE_FILE_NAME: &str = "hashes.bin";
+
+/// The metadata a cached hash is validated against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub size: u64,
+    pub mtime_ns: i128,
+    pub ctime_ns: i128,
+    pub inode: u64,
+}
+
+impl FileStamp {
+    /// Captures the identifying metadata of a file.
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            FileStamp {
+                size: metadata.len(),
+                mtime_ns: metadata.mtime() as i128 * 1_000_000_000 + metadata.mtime_nsec() as i128,
+                ctime_ns: metadata.ctime() as i128 * 1_000_000_000 + metadata.ctime_nsec() as i128,
+                inode: metadata.ino(),
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let mtime_ns = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_nanos() as i128);
+            FileStamp {
+                size: metadata.len(),
+                mtime_ns,
+                ctime_ns: 0,
+                inode: 0,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    stamp: FileStamp,
+    hash: String,
+}
+
+/// Paths are stored as raw bytes so names that are not valid UTF-8 round-trip exactly.
+type CacheEntries = HashMap<(HashAlgorithm, Vec<u8>), CacheEntry>;
+
+#[derive(Deserialize)]
+struct CacheFile {
+    version: u32,
+    entries: CacheEntries,
+}
+
+/// Borrowing twin of [`CacheFile`] so saving does not copy every entry.
+#[derive(Serialize)]
+struct CacheFileRef<'a> {
+    version: u32,
+    entries: &'a CacheEntries,
+}
+
+/// Summary numbers for the `cache stats` command.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Total number of cached hashes.
+    pub entries: usize,
+    /// Number of cached hashes per algorithm.
+    pub per_algorithm: Vec<(HashAlgorithm, usize)>,
+    /// Size of the cache file on disk, in bytes (0 if it has not been written yet).
+    pub file_size: u64,
+}
+
+/// A thread-safe hash cache backed by a compact binary file.
+#[derive(Debug)]
+pub struct HashCache {
+    path: PathBuf,
+    entries: Mutex<CacheEntries>,
+}
+
+impl HashCache {
+    /// Returns the default cache location: `$XDG_CACHE_HOME/hashlaser/hashes.bin`,
+    /// falling back to `$HOME/.cache/hashlaser/hashes.bin`.
+    pub fn default_path() -> Option<PathBuf> {
+        let base = env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+        Some(base.join("hashlaser").join(CACHE_FILE_NAME))
+    }
+
+    /// Loads the cache stored at `path`.
+    ///
+    /// A missing file yields an empty cache. A file that cannot be decoded, or was
+    /// written by another cache version, is treated as empty and replaced on the next save.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let entries = match File::open(&path) {
+            Ok(file) => match bincode::deserialize_from::<_, CacheFile>(BufReader::new(file)) {
+                Ok(cache) if cache.version == CACHE_VERSION => cache.entries,
+                _ => HashMap::new(),
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(HashCache {
+            path,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// The file this cache is loaded from and saved to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the cached hash for `path` if its metadata still matches `stamp`.
+    pub fn get<P: AsRef<Path>>(
+        &self,
+        path: P,
+        algorithm: HashAlgorithm,
+        stamp: &FileStamp,
+    ) -> Option<String> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(&(algorithm, path_to_bytes(path.as_ref()).into_owned()))
+            .filter(|entry| entry.stamp == *stamp)
+            .map(|entry| entry.hash.clone())
+    }
+
+    /// Records the hash of `path`, replacing any stale entry.
+    pub fn insert<P: AsRef<Path>>(
+        &self,
+        path: P,
+        algorithm: HashAlgorithm,
+        stamp: FileStamp,
+        hash: String,
+    ) {
+        let mut entries = self.entries.lock().unwrap();
+        let key = (algorithm, path_to_bytes(path.as_ref()).into_owned());
+        entries.insert(key, CacheEntry { stamp, hash });
+    }
+
+    /// Removes entries whose file has vanished or whose metadata no longer matches.
+    ///
+    /// # Returns
+    ///
+    /// The number of entries removed.
+    pub fn prune(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|(_, path), entry| {
+            fs::metadata(path_from_bytes(path.clone()))
+                .map(|metadata| FileStamp::from_metadata(&metadata) == entry.stamp)
+                .unwrap_or(false)
+        });
+        before - entries.len()
+    }
+
+    /// Computes summary statistics about the cache.
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.entries.lock().unwrap();
+        let per_algorithm = HashAlgorithm::ALL
+            .into_iter()
+            .map(|algo| (algo, entries.keys().filter(|(a, _)| *a == algo).count()))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        CacheStats {
+            entries: entries.len(),
+            per_algorithm,
+            file_size: fs::metadata(&self.path).map_or(0, |m| m.len()),
+        }
+    }
+
+    /// Writes the cache back to disk, creating its directory if necessary.
+    ///
+    /// The data is written to a temporary file first and renamed into place, so an
+    /// interrupted save never leaves a truncated cache behind.
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_path = self.path.with_extension("bin.tmp");
+        {
+            let entries = self.entries.lock().unwrap();
+            let cache = CacheFileRef {
+                version: CACHE_VERSION,
+                entries: &entries,
+            };
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            bincode::serialize_into(&mut writer, &cache).map_err(io::Error::other)?;
+            writer.flush()?;
+        }
+        fs::rename(&tmp_path, &self.path)
+    }
+}
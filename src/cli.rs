@@ -0,0 +1,517 @@
+//! Command-line interface definition.
+//!
+//! Every subcommand and option is declared here with clap, so values are parsed into
+//! their real types before anything runs: an unknown flag, a non-numeric `--min` or an
+//! invalid `--name-regex` stops the program with an error instead of silently turning into
+//! "no filter" and widening what a `delete` run touches.
+
+use clap::{Args, Parser, Subcommand};
+use globset::Glob;
+use intelligent_file_deduplicator::{
+    content_type::{MediaKind, MimePattern},
+    expr::Expr,
+    filter::{ExcludePattern, FilterOptions, TimeBound, TimeField, TimeFilter},
+    hashing::HashAlgorithm,
+    keep::KeepPolicy,
+    ownership::{GroupId, PermMask, UserId},
+    safe_delete::DeleteMode,
+    scanner::SymlinkPolicy,
+    size::{ByteSize, SizeFormat},
+    timestamp::Timestamp,
+};
+use regex::Regex;
+use std::{fs, path::PathBuf};
+
+/// 🔒 Intelligent File Deduplicator: find, report and safely remove duplicate files.
+#[derive(Debug, Parser)]
+#[command(
+    name = "hashlaser",
+    bin_name = "hashlaser",
+    version,
+    arg_required_else_help = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+    /// Print sizes as exact byte counts instead of human units
+    #[arg(long, global = true)]
+    pub bytes: bool,
+}
+
+impl Cli {
+    pub fn size_format(&self) -> SizeFormat {
+        if self.bytes {
+            SizeFormat::Bytes
+        } else {
+            SizeFormat::Human
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// 🔍 Compare two files
+    Compare(CompareArgs),
+    /// 🧪 Scan a directory for duplicates
+    Scan(ScanArgs),
+    /// 📄 Generate a JSON report of duplicates
+    Report(ReportArgs),
+    /// 🗑️ Delete duplicate files
+    Delete(DeleteArgs),
+    /// 🔗 Replace duplicates with hardlinks, reflinks or symlinks
+    Link(LinkArgs),
+    /// 🎯 Scan with filtering
+    Filter(ScanArgs),
+    /// 🧭 Find every copy of given files
+    FindCopies(FindCopiesArgs),
+    /// 🗃️ Inspect or prune the hash cache
+    Cache(CacheArgs),
+    /// ⏪ Undo a delete or link run
+    Restore(RestoreArgs),
+}
+
+impl Command {
+    /// Checks constraints between options that clap cannot express on its own.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Command::Scan(args) | Command::Filter(args) => {
+                args.roots.validate(&args.dirs)?;
+                args.scan.filter.validate()
+            }
+            Command::Report(args) => {
+                args.roots.validate(&args.dirs)?;
+                args.scan.filter.validate()
+            }
+            Command::Delete(args) => {
+                args.roots.validate(&args.dirs)?;
+                args.action.only_from(&args.dirs)?;
+                args.scan.filter.validate()
+            }
+            Command::Link(args) => {
+                args.roots.validate(&args.dirs)?;
+                args.action.only_from(&args.dirs)?;
+                args.scan.filter.validate()
+            }
+            Command::FindCopies(args) => args.scan.filter.validate(),
+            Command::Compare(_) | Command::Cache(_) | Command::Restore(_) => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct CompareArgs {
+    pub file1: PathBuf,
+    pub file2: PathBuf,
+    /// Hash algorithm: sha256, blake3 or xxh3
+    #[arg(long, value_name = "ALGO", default_value_t)]
+    pub algo: HashAlgorithm,
+}
+
+/// Arguments of `scan` and `filter`.
+#[derive(Debug, Args)]
+pub struct ScanArgs {
+    /// Directories to scan; duplicates are found within and across them
+    #[arg(value_name = "DIR", required = true)]
+    pub dirs: Vec<PathBuf>,
+    #[command(flatten)]
+    pub roots: RootArgs,
+    #[command(flatten)]
+    pub scan: ScanOptionArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct FindCopiesArgs {
+    /// Files to look for
+    #[arg(value_name = "FILE", required = true)]
+    pub files: Vec<PathBuf>,
+    /// Directories to search (one or more)
+    #[arg(long = "in", value_name = "DIR", required = true, num_args = 1..)]
+    pub dirs: Vec<PathBuf>,
+    #[command(flatten)]
+    pub scan: ScanOptionArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ReportArgs {
+    /// Directories to scan; duplicates are found within and across them
+    #[arg(value_name = "DIR", required = true)]
+    pub dirs: Vec<PathBuf>,
+    /// Where to write the JSON report
+    pub output: PathBuf,
+    #[command(flatten)]
+    pub roots: RootArgs,
+    #[command(flatten)]
+    pub scan: ScanOptionArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct DeleteArgs {
+    /// Directories to scan; duplicates are found within and across them
+    #[arg(value_name = "DIR", required = true)]
+    pub dirs: Vec<PathBuf>,
+    /// Move copies into the freedesktop.org trash instead of deleting them
+    #[arg(long, conflicts_with = "quarantine")]
+    pub trash: bool,
+    /// Move copies into this directory, mirroring their original paths
+    #[arg(long, value_name = "DIR")]
+    pub quarantine: Option<PathBuf>,
+    #[command(flatten)]
+    pub action: ActionArgs,
+    #[command(flatten)]
+    pub roots: RootArgs,
+    #[command(flatten)]
+    pub scan: ScanOptionArgs,
+}
+
+impl DeleteArgs {
+    pub fn mode(&self) -> DeleteMode {
+        match (&self.quarantine, self.trash) {
+            (Some(dir), _) => DeleteMode::Quarantine(dir.clone()),
+            (None, true) => DeleteMode::Trash,
+            (None, false) => DeleteMode::Remove,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct LinkArgs {
+    /// Directories to scan; duplicates are found within and across them
+    #[arg(value_name = "DIR", required = true)]
+    pub dirs: Vec<PathBuf>,
+    /// Share extents with copy-on-write clones (Btrfs/XFS), keeping files independent
+    #[arg(long, conflicts_with = "symlink")]
+    pub reflink: bool,
+    /// Replace duplicates with symbolic links (absolute by default)
+    #[arg(long)]
+    pub symlink: bool,
+    /// Make symlink targets relative to each link's directory
+    #[arg(long, requires = "symlink")]
+    pub relative: bool,
+    #[command(flatten)]
+    pub action: ActionArgs,
+    #[command(flatten)]
+    pub roots: RootArgs,
+    #[command(flatten)]
+    pub scan: ScanOptionArgs,
+}
+
+impl LinkArgs {
+    pub fn mode(&self) -> DeleteMode {
+        if self.reflink {
+            DeleteMode::Reflink
+        } else if self.symlink {
+            DeleteMode::Symlink {
+                relative: self.relative,
+            }
+        } else {
+            DeleteMode::Hardlink
+        }
+    }
+}
+
+/// Options shared by `delete` and `link`.
+#[derive(Debug, Args)]
+#[command(next_help_heading = "Safety")]
+pub struct ActionArgs {
+    /// Only print what would be done
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Skip the byte-for-byte comparison with the kept copy
+    #[arg(long)]
+    pub no_verify: bool,
+    /// Which copy survives: oldest, newest, shortest, longest, alpha or prefer:<dir>, comma-separated
+    #[arg(long, value_name = "RULES")]
+    pub keep: Option<KeepPolicy>,
+    /// Where to write the undo journal; the file must not exist yet
+    #[arg(long, value_name = "FILE")]
+    pub journal: Option<PathBuf>,
+    /// Only delete or replace copies under this scanned directory; copies elsewhere are kept (repeatable)
+    #[arg(long, value_name = "DIR")]
+    pub only_from: Vec<PathBuf>,
+}
+
+impl ActionArgs {
+    /// `--only-from`, spelled like the matching scanned directories.
+    pub fn only_from(&self, dirs: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+        resolve_roots("--only-from", dirs, &self.only_from)
+    }
+}
+
+/// Options shared by every command that scans a directory.
+#[derive(Debug, Args)]
+#[command(next_help_heading = "Scan options")]
+pub struct ScanOptionArgs {
+    /// Hash algorithm: sha256, blake3 or xxh3
+    #[arg(long, value_name = "ALGO", default_value_t)]
+    pub algo: HashAlgorithm,
+    /// Do not read or update the hash cache
+    #[arg(long)]
+    pub no_cache: bool,
+    /// Use this hash cache file instead of the default one
+    #[arg(long, value_name = "FILE", conflicts_with = "no_cache")]
+    pub cache_file: Option<PathBuf>,
+    /// Follow symbolic links, skipping directories already visited
+    #[arg(long, overrides_with = "no_follow")]
+    pub follow_symlinks: bool,
+    /// List symbolic links without following them (the default)
+    #[arg(long, overrides_with = "follow_symlinks")]
+    pub no_follow: bool,
+    /// Skip paths listed in .gitignore, .ignore and .hashlaserignore files
+    #[arg(long)]
+    pub respect_ignore: bool,
+    #[command(flatten)]
+    pub filter: FilterArgs,
+}
+
+impl ScanOptionArgs {
+    pub fn symlink_policy(&self) -> SymlinkPolicy {
+        if self.follow_symlinks {
+            SymlinkPolicy::Follow
+        } else {
+            SymlinkPolicy::NoFollow
+        }
+    }
+}
+
+/// Options selecting duplicate groups by the scanned directories they span.
+#[derive(Debug, Args)]
+#[command(next_help_heading = "Multiple directories")]
+pub struct RootArgs {
+    /// Only report duplicates found under at least two of the scanned directories
+    #[arg(long)]
+    pub cross_root: bool,
+    /// Only report duplicates with a copy under this scanned directory (repeatable)
+    #[arg(long, value_name = "DIR")]
+    pub present_in: Vec<PathBuf>,
+}
+
+impl RootArgs {
+    fn validate(&self, dirs: &[PathBuf]) -> Result<(), String> {
+        if self.cross_root && dirs.len() < 2 {
+            return Err("--cross-root needs at least two directories to scan".to_string());
+        }
+        self.present_in(dirs).map(drop)
+    }
+
+    /// `--present-in`, spelled like the matching scanned directories.
+    pub fn present_in(&self, dirs: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+        resolve_roots("--present-in", dirs, &self.present_in)
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(next_help_heading = "Filters")]
+pub struct FilterArgs {
+    /// Skip files smaller than this, e.g. 500k, 10MiB or 2G
+    #[arg(long, value_name = "SIZE")]
+    pub min: Option<ByteSize>,
+    /// Skip files larger than this, e.g. 500k, 10MiB or 2G
+    #[arg(long, value_name = "SIZE")]
+    pub max: Option<ByteSize>,
+    /// Only include these extensions, e.g. txt,csv
+    #[arg(long, value_name = "EXT", value_delimiter = ',')]
+    pub ext: Vec<String>,
+    /// Only include files whose name matches this regular expression
+    #[arg(long, value_name = "PATTERN", alias = "regex")]
+    pub name_regex: Option<Regex>,
+    /// Only include files whose path relative to the scanned directory matches this regular expression
+    #[arg(long, value_name = "PATTERN")]
+    pub path_regex: Option<Regex>,
+    /// Only include files whose path relative to the scanned directory matches this glob, e.g. '*/exports/2023/*'
+    #[arg(long, value_name = "GLOB")]
+    pub path_glob: Option<Glob>,
+    /// Skip files and directories matching this glob, e.g. '*.tmp' or 'target/' (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<ExcludePattern>,
+    /// Skip directories with this name, e.g. node_modules or .git (repeatable)
+    #[arg(long, value_name = "NAME")]
+    pub exclude_dir: Vec<String>,
+    /// Skip files with these extensions, e.g. tmp,bak
+    #[arg(long, value_name = "EXT", value_delimiter = ',')]
+    pub exclude_ext: Vec<String>,
+    /// Only include files last modified before this time, e.g. 1y, 30d or 2024-01-31
+    #[arg(long, value_name = "WHEN")]
+    pub modified_before: Option<Timestamp>,
+    /// Only include files last modified after this time, e.g. 6h or 2024-01-31
+    #[arg(long, value_name = "WHEN")]
+    pub modified_after: Option<Timestamp>,
+    /// Only include files last accessed before this time
+    #[arg(long, value_name = "WHEN")]
+    pub accessed_before: Option<Timestamp>,
+    /// Only include files last accessed after this time
+    #[arg(long, value_name = "WHEN")]
+    pub accessed_after: Option<Timestamp>,
+    /// Only include files created before this time, where the filesystem records it
+    #[arg(long, value_name = "WHEN")]
+    pub created_before: Option<Timestamp>,
+    /// Only include files created after this time, where the filesystem records it
+    #[arg(long, value_name = "WHEN")]
+    pub created_after: Option<Timestamp>,
+    /// Only include files modified more recently than this file
+    #[arg(long, value_name = "FILE", value_parser = modification_time)]
+    pub newer_than: Option<Timestamp>,
+    /// Only include files owned by this user name or uid
+    #[arg(long, value_name = "USER")]
+    pub owner: Option<UserId>,
+    /// Only include files belonging to this group name or gid
+    #[arg(long, value_name = "GROUP")]
+    pub group: Option<GroupId>,
+    /// Only include files whose permission bits are exactly MODE, all of -MODE or any of /MODE (octal)
+    #[arg(long, value_name = "MODE", allow_hyphen_values = true)]
+    pub perm: Option<PermMask>,
+    /// Only include files whose content is of this type, detected from magic bytes: image, video, audio, archive, document, book, font or application
+    #[arg(long = "type", value_name = "TYPE", value_delimiter = ',')]
+    pub types: Vec<MediaKind>,
+    /// Only include files whose detected MIME type is this, e.g. application/pdf or image/*
+    #[arg(long = "mime", value_name = "MIME", value_delimiter = ',')]
+    pub mimes: Vec<MimePattern>,
+    /// Only include files satisfying this expression, e.g. "(ext in [jpg,png] and size > 1MiB) or name ~ 'backup'"
+    #[arg(long = "where", value_name = "EXPR")]
+    pub expression: Option<Expr>,
+}
+
+/// Maps each directory given to `option` to the scanned directory it names, compared
+/// by canonical path, so that it matches the scanned paths by prefix.
+fn resolve_roots(
+    option: &str,
+    dirs: &[PathBuf],
+    given: &[PathBuf],
+) -> Result<Vec<PathBuf>, String> {
+    given
+        .iter()
+        .map(|dir| {
+            let canonical = fs::canonicalize(dir).ok();
+            dirs.iter()
+                .find(|root| {
+                    *root == dir || canonical.is_some() && fs::canonicalize(root).ok() == canonical
+                })
+                .cloned()
+                .ok_or_else(|| {
+                    format!(
+                        "{option} {} is not one of the directories being scanned",
+                        dir.display()
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Reads the modification time of the `--newer-than` reference file.
+fn modification_time(path: &str) -> Result<Timestamp, String> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map(Timestamp)
+        .map_err(|e| format!("cannot read the modification time of '{path}': {e}"))
+}
+
+impl FilterArgs {
+    fn validate(&self) -> Result<(), String> {
+        if let (Some(min), Some(max)) = (self.min, self.max)
+            && min > max
+        {
+            return Err(format!(
+                "--min ({min}) is larger than --max ({max}); no file could match"
+            ));
+        }
+        if self.ext.iter().any(|ext| ext.trim().is_empty()) {
+            return Err("--ext contains an empty extension".to_string());
+        }
+        if self.exclude_ext.iter().any(|ext| ext.trim().is_empty()) {
+            return Err("--exclude-ext contains an empty extension".to_string());
+        }
+        if self.exclude_dir.iter().any(|dir| dir.trim().is_empty()) {
+            return Err("--exclude-dir needs a directory name".to_string());
+        }
+        let ranges = [
+            (
+                "--modified",
+                self.modified_after.max(self.newer_than),
+                self.modified_before,
+            ),
+            ("--accessed", self.accessed_after, self.accessed_before),
+            ("--created", self.created_after, self.created_before),
+        ];
+        for (option, after, before) in ranges {
+            if let (Some(after), Some(before)) = (after, before)
+                && after >= before
+            {
+                return Err(format!(
+                    "{option}-before ({before}) is not later than the lower bound ({after}); no file could match"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn options(&self) -> FilterOptions {
+        FilterOptions {
+            min_size: self.min.map(|size| size.0),
+            max_size: self.max.map(|size| size.0),
+            extensions: (!self.ext.is_empty())
+                .then(|| self.ext.iter().map(|ext| ext.trim().to_string()).collect()),
+            regex: self.name_regex.clone(),
+            path_regex: self.path_regex.clone(),
+            path_glob: self.path_glob.as_ref().map(Glob::compile_matcher),
+            exclude: self.exclude.clone(),
+            exclude_dirs: self.exclude_dir.clone(),
+            exclude_extensions: self
+                .exclude_ext
+                .iter()
+                .map(|ext| ext.trim().to_string())
+                .collect(),
+            times: self.time_filters(),
+            owner: self.owner.map(|user| user.0),
+            group: self.group.map(|group| group.0),
+            perm: self.perm,
+            types: self.types.clone(),
+            mimes: self.mimes.clone(),
+            expression: self.expression.clone(),
+        }
+    }
+
+    fn time_filters(&self) -> Vec<TimeFilter> {
+        use TimeField::*;
+        let before = |time: Option<Timestamp>| time.map(|time| TimeBound::Before(time.0));
+        let after = |time: Option<Timestamp>| time.map(|time| TimeBound::After(time.0));
+        [
+            (Modified, before(self.modified_before)),
+            (Modified, after(self.modified_after)),
+            (Modified, after(self.newer_than)),
+            (Accessed, before(self.accessed_before)),
+            (Accessed, after(self.accessed_after)),
+            (Created, before(self.created_before)),
+            (Created, after(self.created_after)),
+        ]
+        .into_iter()
+        .filter_map(|(field, bound)| {
+            Some(TimeFilter {
+                field,
+                bound: bound?,
+            })
+        })
+        .collect()
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    pub action: CacheAction,
+    /// Use this hash cache file instead of the default one
+    #[arg(long, value_name = "FILE", global = true)]
+    pub cache_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Subcommand)]
+pub enum CacheAction {
+    /// Show the number of entries per algorithm and the size on disk
+    Stats,
+    /// Drop entries for files that changed or no longer exist
+    Prune,
+}
+
+#[derive(Debug, Args)]
+pub struct RestoreArgs {
+    /// Journal written by a delete or link run
+    pub journal: PathBuf,
+}
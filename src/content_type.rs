@@ -0,0 +1,150 @@
+//! Content types detected from a file's leading magic bytes rather than its name.
+//!
+//! A `.dat` file that is really a JPEG is detected as `image/jpeg`. Detection reads at
+//! most [`SNIFF_LEN`] bytes and recognises common binary formats (images, video, audio,
+//! archives, documents, fonts, executables); plain text and unknown formats have no
+//! detected type.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read},
+    path::Path,
+    str::FromStr,
+};
+
+/// How many leading bytes are read to detect a file's type.
+pub const SNIFF_LEN: u64 = 8192;
+
+/// A detected content type, such as `image/jpeg`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentType {
+    pub mime: &'static str,
+    pub kind: MediaKind,
+}
+
+/// The broad category of a content type, as given to `--type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaKind {
+    Image,
+    Video,
+    Audio,
+    Archive,
+    Document,
+    Book,
+    Font,
+    Application,
+    Other,
+}
+
+impl MediaKind {
+    fn from_matcher(matcher: infer::MatcherType) -> Self {
+        use infer::MatcherType;
+        match matcher {
+            MatcherType::Image => MediaKind::Image,
+            MatcherType::Video => MediaKind::Video,
+            MatcherType::Audio => MediaKind::Audio,
+            MatcherType::Archive => MediaKind::Archive,
+            MatcherType::Doc => MediaKind::Document,
+            MatcherType::Book => MediaKind::Book,
+            MatcherType::Font => MediaKind::Font,
+            MatcherType::App => MediaKind::Application,
+            MatcherType::Text | MatcherType::Custom => MediaKind::Other,
+        }
+    }
+}
+
+impl FromStr for MediaKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "image" => Ok(MediaKind::Image),
+            "video" => Ok(MediaKind::Video),
+            "audio" => Ok(MediaKind::Audio),
+            "archive" => Ok(MediaKind::Archive),
+            "document" | "doc" => Ok(MediaKind::Document),
+            "book" => Ok(MediaKind::Book),
+            "font" => Ok(MediaKind::Font),
+            "application" | "app" => Ok(MediaKind::Application),
+            _ => Err(format!(
+                "unknown file type '{s}' (expected image, video, audio, archive, document, book, font or application)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MediaKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MediaKind::Image => "image",
+            MediaKind::Video => "video",
+            MediaKind::Audio => "audio",
+            MediaKind::Archive => "archive",
+            MediaKind::Document => "document",
+            MediaKind::Book => "book",
+            MediaKind::Font => "font",
+            MediaKind::Application => "application",
+            MediaKind::Other => "other",
+        })
+    }
+}
+
+/// A MIME type to match, such as `application/pdf`, or a whole family such as `image/*`.
+/// Compared case-insensitively.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MimePattern(String);
+
+impl MimePattern {
+    pub fn matches(&self, mime: &str) -> bool {
+        match self.0.strip_suffix("/*") {
+            Some(family) => mime
+                .split_once('/')
+                .is_some_and(|(prefix, _)| prefix.eq_ignore_ascii_case(family)),
+            None => mime.eq_ignore_ascii_case(&self.0),
+        }
+    }
+}
+
+impl FromStr for MimePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.split_once('/') {
+            Some((family, subtype))
+                if !family.is_empty() && !subtype.is_empty() && !subtype.contains('/') =>
+            {
+                Ok(MimePattern(s.to_string()))
+            }
+            _ => Err(format!(
+                "invalid MIME type '{s}' (expected e.g. application/pdf or image/*)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MimePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Detects the content type of the file at `path` from its magic bytes.
+///
+/// # Returns
+///
+/// `Ok(None)` if the format is not recognised, or an error if the file cannot be read.
+pub fn detect(path: &Path) -> io::Result<Option<ContentType>> {
+    let mut buffer = Vec::with_capacity(SNIFF_LEN as usize);
+    File::open(path)?.take(SNIFF_LEN).read_to_end(&mut buffer)?;
+    Ok(detect_bytes(&buffer))
+}
+
+/// Detects a content type from the leading bytes of a file.
+pub fn detect_bytes(bytes: &[u8]) -> Option<ContentType> {
+    infer::get(bytes).map(|found| ContentType {
+        mime: found.mime_type(),
+        kind: MediaKind::from_matcher(found.matcher_type()),
+    })
+}
@@ -0,0 +1,192 @@
+//! Locating every copy of a few known files, without a full duplicate scan.
+//!
+//! Where [`crate::scanner::scan_roots`] groups every file of a tree with every other,
+//! [`find_copies`] only answers "where else does this file live?". Files whose size
+//! differs from every reference are dismissed from their metadata alone, so only
+//! same-size candidates are ever read and hashed.
+
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::AtomicUsize,
+};
+
+use crate::error::DedupError;
+use crate::filter::FilterOptions;
+use crate::scanner::{ScanOptions, Walked, file_id, full_hash, walk_roots};
+
+/// The copies found of one reference file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyMatch {
+    /// The reference file as given.
+    pub reference: PathBuf,
+    /// Its size in bytes.
+    pub size: u64,
+    /// Its full content hash under [`ScanOptions::algorithm`].
+    pub hash: String,
+    /// Other files with identical content, sorted by path.
+    pub copies: Vec<PathBuf>,
+    /// Paths that are hardlinks to the reference itself, sharing its storage, sorted.
+    pub hardlinks: Vec<PathBuf>,
+}
+
+/// The outcome of [`find_copies`].
+#[derive(Debug, Default)]
+pub struct CopiesResult {
+    /// One entry per reference that could be read, in the order given.
+    pub matches: Vec<CopyMatch>,
+    /// Files found under the searched directories that passed the filters.
+    pub files_scanned: usize,
+    /// Files sharing their size with a reference, and therefore hashed.
+    pub size_candidates: usize,
+    /// References and searched paths that could not be read, sorted by path.
+    pub errors: Vec<DedupError>,
+}
+
+/// Finds every file under `dirs` with the same content as one of `references`.
+///
+/// The directories are walked like a scan, honouring `filters` and the traversal
+/// settings of `options`. A file is only hashed if its size equals that of a reference,
+/// and hashes go through [`ScanOptions::cache`] when one is configured. The reference
+/// itself is never reported as its own copy, and hardlinks to it are listed separately.
+///
+/// # Arguments
+///
+/// * `references` - The files to look for.
+/// * `dirs` - The directories to search.
+/// * `filters` - Filter options to apply to each file found.
+/// * `options` - Hash algorithm, cache and traversal settings.
+///
+/// # Returns
+///
+/// A [`CopiesResult`] with a [`CopyMatch`] for every readable reference, whether or not
+/// any copies were found.
+pub fn find_copies<P: AsRef<Path>, Q: AsRef<Path>>(
+    references: &[P],
+    dirs: &[Q],
+    filters: &FilterOptions,
+    options: &ScanOptions,
+) -> CopiesResult {
+    let cache_hits = AtomicUsize::new(0);
+    let hash = |path: &Path| {
+        full_hash(
+            path,
+            options.algorithm,
+            options.cache.as_deref(),
+            &cache_hits,
+        )
+    };
+    let mut result = CopiesResult::default();
+
+    // Hash the references first; their sizes decide which files are worth reading
+    let mut matches = Vec::new();
+    let mut identities = Vec::new();
+    for reference in references {
+        let reference = reference.as_ref();
+        let found = fs::metadata(reference).and_then(|metadata| {
+            if !metadata.is_file() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "not a regular file",
+                ));
+            }
+            Ok((
+                metadata.len(),
+                (file_id(&metadata), fs::canonicalize(reference).ok()),
+                hash(reference)?,
+            ))
+        });
+        match found {
+            Ok((size, identity, hash)) => {
+                matches.push(CopyMatch {
+                    reference: reference.to_path_buf(),
+                    size,
+                    hash,
+                    copies: Vec::new(),
+                    hardlinks: Vec::new(),
+                });
+                identities.push(identity);
+            }
+            Err(e) => result.errors.push(DedupError::from_io(reference, e)),
+        }
+    }
+    let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (index, found) in matches.iter().enumerate() {
+        by_size.entry(found.size).or_default().push(index);
+    }
+
+    let dirs: Vec<PathBuf> = dirs.iter().map(|dir| dir.as_ref().to_path_buf()).collect();
+    let Walked {
+        mut files,
+        mut errors,
+        ..
+    } = walk_roots(&dirs, filters, options);
+    files.sort();
+    files.dedup();
+    result.files_scanned = files.len();
+
+    // Keep files of a reference's size; hardlinks to a reference need no hashing
+    let mut candidates = Vec::new();
+    for path in files {
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                errors.push(DedupError::from_io(&path, e));
+                continue;
+            }
+        };
+        let Some(indices) = by_size.get(&metadata.len()) else {
+            continue;
+        };
+        let identity = file_id(&metadata);
+        let canonical = fs::canonicalize(&path).ok();
+        let mut wanted = Vec::new();
+        for &index in indices {
+            let (reference_identity, reference_canonical) = &identities[index];
+            if canonical.is_some() && canonical == *reference_canonical {
+                // The reference itself, or a followed symlink to it
+                continue;
+            }
+            if identity.is_some() && identity == *reference_identity {
+                matches[index].hardlinks.push(path.clone());
+            } else {
+                wanted.push(index);
+            }
+        }
+        if !wanted.is_empty() {
+            candidates.push((path, wanted));
+        }
+    }
+    result.size_candidates = candidates.len();
+
+    let hashed: Vec<(PathBuf, Vec<usize>, io::Result<String>)> = candidates
+        .into_par_iter()
+        .map(|(path, indices)| {
+            let hash = hash(&path);
+            (path, indices, hash)
+        })
+        .collect();
+    for (path, indices, hash) in hashed {
+        match hash {
+            Ok(hash) => {
+                for index in indices {
+                    if matches[index].hash == hash {
+                        matches[index].copies.push(path.clone());
+                    }
+                }
+            }
+            Err(e) => errors.push(DedupError::from_io(&path, e)),
+        }
+    }
+
+    for found in &mut matches {
+        found.copies.sort();
+        found.hardlinks.sort();
+    }
+    result.matches = matches;
+    result.errors.extend(errors);
+    result.errors.sort_by(|a, b| a.path().cmp(b.path()));
+    result
+}
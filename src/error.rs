@@ -0,0 +1,104 @@
+//! The crate-wide error type.
+//!
+//! Scans and delete runs keep going when a single path fails; instead of printing and
+//! forgetting such failures, they collect them as [`DedupError`]s so callers can report
+//! them and tell a partial run from a complete one.
+
+use std::{
+    error::Error,
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+/// Something that went wrong for one path during a scan or delete run.
+#[derive(Debug)]
+pub enum DedupError {
+    /// The path could not be read or modified for lack of permissions.
+    PermissionDenied { path: PathBuf },
+    /// The file disappeared between being listed and being used.
+    Vanished { path: PathBuf },
+    /// The filesystem or platform cannot perform the requested operation.
+    Unsupported { path: PathBuf, reason: String },
+    /// A link could not be created because the file and the kept copy are on
+    /// different filesystems.
+    DifferentFilesystem { path: PathBuf, kept: PathBuf },
+    /// Byte-for-byte verification found the file differs from the kept copy.
+    ContentMismatch { path: PathBuf, kept: PathBuf },
+    /// The undo journal could not be written.
+    Journal { path: PathBuf, source: io::Error },
+    /// Any other I/O error.
+    Io { path: PathBuf, source: io::Error },
+}
+
+impl DedupError {
+    /// Classifies an I/O error that occurred while working on `path`.
+    pub fn from_io(path: impl AsRef<Path>, error: io::Error) -> Self {
+        let path = path.as_ref().to_path_buf();
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => DedupError::PermissionDenied { path },
+            io::ErrorKind::NotFound => DedupError::Vanished { path },
+            io::ErrorKind::Unsupported => DedupError::Unsupported {
+                path,
+                reason: error.to_string(),
+            },
+            _ => DedupError::Io {
+                path,
+                source: error,
+            },
+        }
+    }
+
+    /// The path the error is about.
+    pub fn path(&self) -> &Path {
+        match self {
+            DedupError::PermissionDenied { path }
+            | DedupError::Vanished { path }
+            | DedupError::Unsupported { path, .. }
+            | DedupError::DifferentFilesystem { path, .. }
+            | DedupError::ContentMismatch { path, .. }
+            | DedupError::Journal { path, .. }
+            | DedupError::Io { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for DedupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DedupError::PermissionDenied { path } => {
+                write!(f, "{}: permission denied", path.display())
+            }
+            DedupError::Vanished { path } => {
+                write!(f, "{}: no longer exists", path.display())
+            }
+            DedupError::Unsupported { path, reason } => {
+                write!(f, "{}: {}", path.display(), reason)
+            }
+            DedupError::DifferentFilesystem { path, kept } => write!(
+                f,
+                "{}: not on the same filesystem as {}",
+                path.display(),
+                kept.display()
+            ),
+            DedupError::ContentMismatch { path, kept } => write!(
+                f,
+                "{}: contents differ from {}",
+                path.display(),
+                kept.display()
+            ),
+            DedupError::Journal { path, source } => {
+                write!(f, "cannot write journal {}: {}", path.display(), source)
+            }
+            DedupError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+        }
+    }
+}
+
+impl Error for DedupError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DedupError::Journal { source, .. } | DedupError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
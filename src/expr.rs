@@ -0,0 +1,665 @@
+//! A small boolean language for filters that the individual options cannot express.
+//!
+//! ```text
+//! (ext in [jpg, png] and size > 1MiB) or name ~ 'backup'
+//! ```
+//!
+//! An expression combines comparisons with `and`, `or`, `not` and parentheses; `not`
+//! binds tightest and `or` loosest. Each comparison is a field, an operator and a value:
+//!
+//! | Field | Operators | Values |
+//! |-------|-----------|--------|
+//! | `name`, `path` | `=`, `!=`, `in`, `~`, `!~` | text; `~` is a regular expression |
+//! | `ext` | `=`, `!=`, `in`, `~`, `!~` | extensions, compared case-insensitively |
+//! | `size` | `=`, `!=`, `<`, `<=`, `>`, `>=` | sizes such as `500k` or `1MiB` |
+//! | `modified`, `accessed`, `created` | `=`, `!=`, `<`, `<=`, `>`, `>=` | times such as `30d` or `2024-01-31` |
+//! | `owner`, `group` | `=`, `!=`, `in` | names or numeric ids |
+//!
+//! `path` is relative to the scanned directory. A time is a point in time, so
+//! `modified < 30d` means "last modified more than 30 days ago". Values containing
+//! spaces or any of `()[],=!<>~` must be quoted with `'` or `"`; a backslash escapes the
+//! quote character. A comparison whose file attribute cannot be read is false.
+//!
+//! The individual filter options are built into the same tree by
+//! [`crate::filter::FilterOptions::to_expr`], using a few tests the language does not
+//! spell out, such as [`Expr::Perm`] and [`Expr::Kind`].
+
+use crate::{
+    content_type::{self, ContentType, MediaKind, MimePattern},
+    filter::{ExcludePattern, TimeField},
+    ownership::{GroupId, PermMask, UserId},
+    size::ByteSize,
+    timestamp::Timestamp,
+};
+use globset::GlobMatcher;
+use regex::Regex;
+use std::{
+    cell::OnceCell,
+    cmp::Ordering,
+    error::Error,
+    fmt,
+    fs::{self, Metadata},
+    io,
+    path::Path,
+    str::FromStr,
+    time::SystemTime,
+};
+
+/// Fields a comparison can look at.
+const KNOWN_FIELDS: [&str; 9] = [
+    "name", "path", "ext", "size", "modified", "accessed", "created", "owner", "group",
+];
+
+/// [`KNOWN_FIELDS`] as listed in error messages.
+const FIELDS: &str = "name, path, ext, size, modified, accessed, created, owner or group";
+
+/// A parsed filter expression.
+#[derive(Debug, Clone)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    /// The file name, its path relative to the scan root, or its extension.
+    Text(TextField, TextTest),
+    /// The file size in bytes.
+    Size(Compare, u64),
+    Time(TimeField, Compare, SystemTime),
+    /// The owner is, or is not, one of these uids.
+    Owner(Membership, Vec<u32>),
+    /// The group is, or is not, one of these gids.
+    Group(Membership, Vec<u32>),
+    /// The permission bits satisfy the mask.
+    Perm(PermMask),
+    /// The content, detected from magic bytes, is one of these kinds.
+    Kind(Vec<MediaKind>),
+    /// The detected MIME type matches one of these.
+    Mime(Vec<MimePattern>),
+    /// The pattern excludes the file, by its name or its path relative to the scan root.
+    Excluded(ExcludePattern),
+    /// The file's metadata, or its content when a test needed it, could not be read.
+    /// Filter options accept such files with `or`, so the scan can report them.
+    Unreadable,
+}
+
+/// Which text a [`TextTest`] is applied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextField {
+    Name,
+    Path,
+    Ext,
+}
+
+/// A test on text.
+#[derive(Debug, Clone)]
+pub enum TextTest {
+    /// Equal to one of these values.
+    OneOf(Vec<String>),
+    /// Contains a match of the regular expression.
+    Matches(Regex),
+    /// Matches the glob as a whole; `*` also matches `/`.
+    Glob(GlobMatcher),
+}
+
+/// Whether a file attribute must be among a set of values or outside it.
+///
+/// An attribute that cannot be read is neither, so `owner != …` does not pass
+/// files whose owner is unknown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Membership {
+    In,
+    NotIn,
+}
+
+impl Membership {
+    /// Whether an attribute that is (`found`) or is not among the values passes.
+    fn accepts(self, found: bool) -> bool {
+        match self {
+            Membership::In => found,
+            Membership::NotIn => !found,
+        }
+    }
+}
+
+/// An ordering comparison between a file attribute and a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compare {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Compare {
+    /// Whether an attribute that compares to the value as `ordering` passes.
+    fn accepts(self, ordering: Ordering) -> bool {
+        match self {
+            Compare::Eq => ordering.is_eq(),
+            Compare::Ne => ordering.is_ne(),
+            Compare::Lt => ordering.is_lt(),
+            Compare::Le => ordering.is_le(),
+            Compare::Gt => ordering.is_gt(),
+            Compare::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+impl Expr {
+    /// Evaluates the expression for the file at `path`, whose path relative to the scan
+    /// root is `relative`. Metadata is read at most once, and only if a comparison needs it.
+    pub fn matches(&self, path: &Path, relative: &Path) -> bool {
+        let file = Subject {
+            path,
+            relative,
+            metadata: OnceCell::new(),
+            content: OnceCell::new(),
+        };
+        self.eval(&file)
+    }
+
+    fn eval(&self, file: &Subject) -> bool {
+        match self {
+            Expr::And(left, right) => left.eval(file) && right.eval(file),
+            Expr::Or(left, right) => left.eval(file) || right.eval(file),
+            Expr::Not(inner) => !inner.eval(file),
+            Expr::Text(field, test) => {
+                let text = match field {
+                    TextField::Name => file.path.file_name(),
+                    TextField::Path => Some(file.relative.as_os_str()),
+                    TextField::Ext => file.path.extension(),
+                };
+                let text = text.map(|text| text.to_string_lossy()).unwrap_or_default();
+                match test {
+                    TextTest::OneOf(values) if *field == TextField::Ext => {
+                        values.iter().any(|value| value.eq_ignore_ascii_case(&text))
+                    }
+                    TextTest::OneOf(values) => values.iter().any(|value| *value == text),
+                    TextTest::Matches(re) => re.is_match(&text),
+                    TextTest::Glob(glob) => glob.is_match(&*text),
+                }
+            }
+            Expr::Size(compare, size) => file
+                .metadata()
+                .is_some_and(|metadata| compare.accepts(metadata.len().cmp(size))),
+            Expr::Time(field, compare, time) => file
+                .metadata()
+                .and_then(|metadata| field.of(metadata).ok())
+                .is_some_and(|value| compare.accepts(value.cmp(time))),
+            Expr::Owner(membership, uids) => file
+                .owner()
+                .is_some_and(|(uid, _)| membership.accepts(uids.contains(&uid))),
+            Expr::Group(membership, gids) => file
+                .owner()
+                .is_some_and(|(_, gid)| membership.accepts(gids.contains(&gid))),
+            Expr::Perm(mask) => file.mode().is_some_and(|mode| mask.matches(mode)),
+            Expr::Kind(kinds) => file
+                .content()
+                .is_some_and(|found| kinds.contains(&found.kind)),
+            Expr::Mime(mimes) => file
+                .content()
+                .is_some_and(|found| mimes.iter().any(|mime| mime.matches(found.mime))),
+            Expr::Excluded(pattern) => pattern.matches(file.relative, false),
+            Expr::Unreadable => {
+                file.metadata().is_none() || matches!(file.content.get(), Some(Err(_)))
+            }
+        }
+    }
+}
+
+/// The file an expression is evaluated for.
+struct Subject<'a> {
+    path: &'a Path,
+    relative: &'a Path,
+    metadata: OnceCell<Option<Metadata>>,
+    content: OnceCell<io::Result<Option<ContentType>>>,
+}
+
+impl Subject<'_> {
+    fn metadata(&self) -> Option<&Metadata> {
+        self.metadata
+            .get_or_init(|| fs::metadata(self.path).ok())
+            .as_ref()
+    }
+
+    /// The detected content type, if it is recognised and the file could be read.
+    fn content(&self) -> Option<ContentType> {
+        match self.content.get_or_init(|| content_type::detect(self.path)) {
+            Ok(found) => *found,
+            Err(_) => None,
+        }
+    }
+
+    /// The permission bits; never available off Unix.
+    fn mode(&self) -> Option<u32> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            self.metadata().map(|metadata| metadata.mode())
+        }
+        #[cfg(not(unix))]
+        {
+            None
+        }
+    }
+
+    /// The uid and gid; never available off Unix.
+    fn owner(&self) -> Option<(u32, u32)> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            self.metadata()
+                .map(|metadata| (metadata.uid(), metadata.gid()))
+        }
+        #[cfg(not(unix))]
+        {
+            None
+        }
+    }
+}
+
+/// An expression that failed to parse, pointing at the offending column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based column, counted in characters.
+    pub column: usize,
+    pub message: String,
+    expression: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} at column {}", self.message, self.column)?;
+        writeln!(f, "  {}", self.expression)?;
+        write!(f, "  {:>width$}", "^", width = self.column)
+    }
+}
+
+impl Error for ParseError {}
+
+impl FromStr for Expr {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fail = |(column, message)| ParseError {
+            column,
+            message,
+            expression: s.to_string(),
+        };
+        let tokens = tokenize(s).map_err(fail)?;
+        let mut parser = Parser { tokens, next: 0 };
+        let expr = parser.or().map_err(fail)?;
+        match parser.peek() {
+            Token {
+                kind: Kind::End, ..
+            } => Ok(expr),
+            token => Err(fail(token.error(format!(
+                "expected 'and', 'or' or the end of the expression, found {}",
+                token.kind
+            )))),
+        }
+    }
+}
+
+/// A column and a message; turned into a [`ParseError`] once the input is attached.
+type Failure = (usize, String);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Kind {
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    Comma,
+    /// One of `=`, `==`, `!=`, `<`, `<=`, `>`, `>=`, `~` and `!~`.
+    Operator(&'static str),
+    Word(String),
+    Quoted(String),
+    End,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::LeftParen => f.write_str("'('"),
+            Kind::RightParen => f.write_str("')'"),
+            Kind::LeftBracket => f.write_str("'['"),
+            Kind::RightBracket => f.write_str("']'"),
+            Kind::Comma => f.write_str("','"),
+            Kind::Operator(op) => write!(f, "'{op}'"),
+            Kind::Word(word) => write!(f, "'{word}'"),
+            Kind::Quoted(text) => write!(f, "\"{text}\""),
+            Kind::End => f.write_str("the end of the expression"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: Kind,
+    column: usize,
+}
+
+impl Token {
+    fn error(&self, message: String) -> Failure {
+        (self.column, message)
+    }
+
+    /// The keyword this token spells, lowercased, if it is a bare word.
+    fn keyword(&self) -> Option<String> {
+        match &self.kind {
+            Kind::Word(word) => Some(word.to_ascii_lowercase()),
+            _ => None,
+        }
+    }
+}
+
+/// Characters that end a bare word.
+fn is_special(c: char) -> bool {
+    c.is_whitespace() || "()[],=!<>~'\"".contains(c)
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, Failure> {
+    const OPERATORS: [&str; 9] = ["==", "!=", "<=", ">=", "!~", "=", "<", ">", "~"];
+
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let single = match c {
+            '(' => Some(Kind::LeftParen),
+            ')' => Some(Kind::RightParen),
+            '[' => Some(Kind::LeftBracket),
+            ']' => Some(Kind::RightBracket),
+            ',' => Some(Kind::Comma),
+            _ => None,
+        };
+        if let Some(kind) = single {
+            tokens.push(Token { kind, column });
+            i += 1;
+        } else if let Some(op) = OPERATORS.iter().find(|op| {
+            op.chars()
+                .enumerate()
+                .all(|(offset, c)| chars.get(i + offset) == Some(&c))
+        }) {
+            tokens.push(Token {
+                kind: Kind::Operator(op),
+                column,
+            });
+            i += op.len();
+        } else if c == '\'' || c == '"' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err((column, format!("unterminated string starting with {c}"))),
+                    Some('\\') if matches!(chars.get(i + 1), Some(&next) if next == c || next == '\\') =>
+                    {
+                        text.push(chars[i + 1]);
+                        i += 2;
+                    }
+                    Some(&next) if next == c => {
+                        i += 1;
+                        break;
+                    }
+                    Some(&next) => {
+                        text.push(next);
+                        i += 1;
+                    }
+                }
+            }
+            tokens.push(Token {
+                kind: Kind::Quoted(text),
+                column,
+            });
+        } else if c == '!' {
+            return Err((
+                column,
+                "unexpected '!' (use 'not', '!=' or '!~')".to_string(),
+            ));
+        } else {
+            let start = i;
+            while i < chars.len() && !is_special(chars[i]) {
+                i += 1;
+            }
+            tokens.push(Token {
+                kind: Kind::Word(chars[start..i].iter().collect()),
+                column,
+            });
+        }
+    }
+    tokens.push(Token {
+        kind: Kind::End,
+        column: chars.len() + 1,
+    });
+    Ok(tokens)
+}
+
+/// A recursive descent parser; each method parses one precedence level.
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next]
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.next].clone();
+        if token.kind != Kind::End {
+            self.next += 1;
+        }
+        token
+    }
+
+    /// Consumes the next token if it is the bare word `keyword`.
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek().keyword().is_some_and(|word| word == keyword);
+        if found {
+            self.advance();
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr, Failure> {
+        let mut expr = self.and()?;
+        while self.eat_keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, Failure> {
+        let mut expr = self.not()?;
+        while self.eat_keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, Failure> {
+        if self.eat_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        if self.peek().kind == Kind::LeftParen {
+            let open = self.advance();
+            let expr = self.or()?;
+            let close = self.advance();
+            if close.kind != Kind::RightParen {
+                return Err(close.error(format!(
+                    "expected ')' to close the '(' at column {}, found {}",
+                    open.column, close.kind
+                )));
+            }
+            return Ok(expr);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, Failure> {
+        let token = self.advance();
+        let field = match token.keyword() {
+            Some(word) if KNOWN_FIELDS.contains(&word.as_str()) => word,
+            Some(word) => {
+                return Err(token.error(format!("unknown field '{word}' (expected {FIELDS})")));
+            }
+            None => {
+                return Err(
+                    token.error(format!("expected a field ({FIELDS}), found {}", token.kind))
+                );
+            }
+        };
+        let token = self.advance();
+        let op = match &token.kind {
+            Kind::Operator(op) => *op,
+            Kind::Word(word) if word.eq_ignore_ascii_case("in") => "in",
+            kind => {
+                return Err(token.error(format!(
+                    "expected an operator such as =, <, ~ or in after '{field}', found {kind}"
+                )));
+            }
+        };
+        let values = if op == "in" {
+            self.list()?
+        } else {
+            vec![self.value()?]
+        };
+        let unsupported = || token.error(format!("'{op}' cannot be used with '{field}'"));
+
+        let expr = match field.as_str() {
+            "name" | "path" | "ext" => {
+                let text_field = match field.as_str() {
+                    "name" => TextField::Name,
+                    "path" => TextField::Path,
+                    _ => TextField::Ext,
+                };
+                let test = match op {
+                    "~" | "!~" => {
+                        let (column, pattern) = &values[0];
+                        let re = Regex::new(pattern)
+                            .map_err(|e| (*column, format!("invalid regular expression: {e}")))?;
+                        TextTest::Matches(re)
+                    }
+                    "=" | "==" | "!=" | "in" => TextTest::OneOf(
+                        values
+                            .into_iter()
+                            .map(|(_, value)| match text_field {
+                                TextField::Ext => value.trim_start_matches('.').to_string(),
+                                _ => value,
+                            })
+                            .collect(),
+                    ),
+                    _ => return Err(unsupported()),
+                };
+                negate_if(op.starts_with('!'), Expr::Text(text_field, test))
+            }
+            "size" => {
+                let (compare, (column, value)) = ordering(op, values).ok_or_else(unsupported)?;
+                let size: ByteSize = value.parse().map_err(|e| (column, e))?;
+                Expr::Size(compare, size.0)
+            }
+            "modified" | "accessed" | "created" => {
+                let time_field = match field.as_str() {
+                    "modified" => TimeField::Modified,
+                    "accessed" => TimeField::Accessed,
+                    _ => TimeField::Created,
+                };
+                let (compare, (column, value)) = ordering(op, values).ok_or_else(unsupported)?;
+                let time: Timestamp = value.parse().map_err(|e| (column, e))?;
+                Expr::Time(time_field, compare, time.0)
+            }
+            "owner" | "group" => {
+                if !matches!(op, "=" | "==" | "!=" | "in") {
+                    return Err(unsupported());
+                }
+                let ids = values
+                    .into_iter()
+                    .map(|(column, value)| {
+                        let id = if field == "owner" {
+                            value.parse::<UserId>().map(|user| user.0)
+                        } else {
+                            value.parse::<GroupId>().map(|group| group.0)
+                        };
+                        id.map_err(|e| (column, e))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let membership = if op == "!=" {
+                    Membership::NotIn
+                } else {
+                    Membership::In
+                };
+                if field == "owner" {
+                    Expr::Owner(membership, ids)
+                } else {
+                    Expr::Group(membership, ids)
+                }
+            }
+            _ => unreachable!("fields are checked against KNOWN_FIELDS"),
+        };
+        Ok(expr)
+    }
+
+    /// A single value with its column.
+    fn value(&mut self) -> Result<(usize, String), Failure> {
+        let token = self.advance();
+        match token.kind.clone() {
+            Kind::Word(word) | Kind::Quoted(word) => Ok((token.column, word)),
+            kind => Err(token.error(format!("expected a value, found {kind}"))),
+        }
+    }
+
+    /// A bracketed, comma-separated list of at least one value.
+    fn list(&mut self) -> Result<Vec<(usize, String)>, Failure> {
+        let open = self.advance();
+        if open.kind != Kind::LeftBracket {
+            return Err(open.error(format!(
+                "expected '[' to start a list after 'in', found {}",
+                open.kind
+            )));
+        }
+        let mut values = vec![self.value()?];
+        loop {
+            let token = self.advance();
+            match token.kind.clone() {
+                Kind::Comma => values.push(self.value()?),
+                Kind::RightBracket => return Ok(values),
+                kind => {
+                    return Err(token.error(format!(
+                        "expected ',' or ']' in the list starting at column {}, found {kind}",
+                        open.column
+                    )));
+                }
+            }
+        }
+    }
+}
+
+/// The [`Compare`] for an ordering operator, with its single value.
+fn ordering(op: &str, mut values: Vec<(usize, String)>) -> Option<(Compare, (usize, String))> {
+    let compare = match op {
+        "=" | "==" => Compare::Eq,
+        "!=" => Compare::Ne,
+        "<" => Compare::Lt,
+        "<=" => Compare::Le,
+        ">" => Compare::Gt,
+        ">=" => Compare::Ge,
+        _ => return None,
+    };
+    Some((compare, values.remove(0)))
+}
+
+fn negate_if(negate: bool, expr: Expr) -> Expr {
+    if negate {
+        Expr::Not(Box::new(expr))
+    } else {
+        expr
+    }
+}
@@ -2,11 +2,73 @@
 ///
 /// The `hash_file` function is typically used to compute a hash value for a given file,
 /// which can be useful for tasks such as file comparison, deduplication, or integrity checking.
-use crate::hashing::hash_file;
-use std::io;
+use crate::hashing::{HashAlgorithm, hash_file_with};
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
 
-pub fn compare_files(path1: &str, path2: &str) -> io::Result<bool> {
-    let hash1 = hash_file(path1)?;
-    let hash2 = hash_file(path2)?;
+/// Size of the chunks compared by [`files_identical`].
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Compares two files by their SHA-256 hash.
+pub fn compare_files<P: AsRef<Path>, Q: AsRef<Path>>(path1: P, path2: Q) -> io::Result<bool> {
+    compare_files_with(path1, path2, HashAlgorithm::Sha256)
+}
+
+/// Compares two files by their hash under the given algorithm.
+pub fn compare_files_with<P: AsRef<Path>, Q: AsRef<Path>>(
+    path1: P,
+    path2: Q,
+    algorithm: HashAlgorithm,
+) -> io::Result<bool> {
+    let hash1 = hash_file_with(path1, algorithm)?;
+    let hash2 = hash_file_with(path2, algorithm)?;
     Ok(hash1 == hash2)
 }
+
+/// Compares two files byte by byte.
+///
+/// Unlike [`compare_files`] this does not rely on hashes at all, so it cannot be fooled
+/// by a collision or a stale grouping. Files of different length are rejected without
+/// reading their contents.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if both files have exactly the same contents.
+pub fn files_identical<P: AsRef<Path>, Q: AsRef<Path>>(path1: P, path2: Q) -> io::Result<bool> {
+    let file1 = File::open(path1)?;
+    let file2 = File::open(path2)?;
+    if file1.metadata()?.len() != file2.metadata()?.len() {
+        return Ok(false);
+    }
+
+    let mut reader1 = BufReader::with_capacity(CHUNK_SIZE, file1);
+    let mut reader2 = BufReader::with_capacity(CHUNK_SIZE, file2);
+    let mut buffer1 = vec![0u8; CHUNK_SIZE];
+    let mut buffer2 = vec![0u8; CHUNK_SIZE];
+
+    loop {
+        let read1 = read_full(&mut reader1, &mut buffer1)?;
+        let read2 = read_full(&mut reader2, &mut buffer2)?;
+        if buffer1[..read1] != buffer2[..read2] {
+            return Ok(false);
+        }
+        if read1 == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Fills `buffer` as far as possible, returning fewer bytes only at end of file.
+fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
@@ -1,17 +1,137 @@
+use crate::{
+    content_type::{MediaKind, MimePattern},
+    expr::{Compare, Expr, Membership, TextField, TextTest},
+    ownership::PermMask,
+};
+use globset::{Glob, GlobMatcher};
 use regex::Regex;
-use std::{ffi::OsStr, fs, path::Path};
+use std::{fs::Metadata, io, path::Path, str::FromStr, time::SystemTime};
 
 /// Structure to hold filtering options
 pub struct FilterOptions {
     pub min_size: Option<u64>, // in bytes
     pub max_size: Option<u64>,
     pub extensions: Option<Vec<String>>,
+    /// Matched against the file name only.
     pub regex: Option<Regex>,
+    /// Matched against the path relative to the scan root, e.g. `exports/2023/a.csv`.
+    pub path_regex: Option<Regex>,
+    /// Matched against the path relative to the scan root; `*` also matches `/`.
+    pub path_glob: Option<GlobMatcher>,
+    /// Glob patterns excluding files, and directories together with everything below them,
+    /// matched against names and paths relative to the scan root.
+    pub exclude: Vec<ExcludePattern>,
+    /// Directory names (or trailing path components such as `assets/cache`) pruned from the walk.
+    pub exclude_dirs: Vec<String>,
+    /// Extensions excluded regardless of `extensions`, compared case-insensitively.
+    pub exclude_extensions: Vec<String>,
+    /// Bounds on modification, access or creation time; a file must satisfy all of them.
+    pub times: Vec<TimeFilter>,
+    /// Only files owned by this uid.
+    pub owner: Option<u32>,
+    /// Only files belonging to this gid.
+    pub group: Option<u32>,
+    /// Only files whose permission bits satisfy this mask.
+    pub perm: Option<PermMask>,
+    /// Only files whose content, detected from magic bytes, is one of these kinds.
+    pub types: Vec<MediaKind>,
+    /// Only files whose detected MIME type matches one of these.
+    pub mimes: Vec<MimePattern>,
+    /// A boolean expression a file must also satisfy, combining tests with and, or and not.
+    /// It becomes part of the tree built by [`FilterOptions::to_expr`].
+    pub expression: Option<Expr>,
+}
+
+/// Which timestamp of a file a [`TimeFilter`] looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeField {
+    Modified,
+    Accessed,
+    Created,
+}
+
+impl TimeField {
+    /// Reads this timestamp from `metadata`; creation time is not available everywhere.
+    pub fn of(self, metadata: &Metadata) -> io::Result<SystemTime> {
+        match self {
+            TimeField::Modified => metadata.modified(),
+            TimeField::Accessed => metadata.accessed(),
+            TimeField::Created => metadata.created(),
+        }
+    }
+}
+
+/// Requires a file's `field` timestamp to lie strictly before or after a point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeFilter {
+    pub field: TimeField,
+    pub bound: TimeBound,
+}
+
+/// One side of a time range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeBound {
+    Before(SystemTime),
+    After(SystemTime),
+}
+
+impl TimeFilter {
+    /// Whether `metadata` satisfies the filter. A timestamp that cannot be read never does.
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        match (self.field.of(metadata), self.bound) {
+            (Ok(time), TimeBound::Before(limit)) => time < limit,
+            (Ok(time), TimeBound::After(limit)) => time > limit,
+            (Err(_), _) => false,
+        }
+    }
+}
+
+/// A glob excluding the entries it matches, such as `*.tmp` or `target/`.
+///
+/// The glob is tried against both the entry's name and its path relative to the scan
+/// root, so `*.tmp` and `node_modules` exclude entries at any depth, while `**/build/*`
+/// or `assets/build/*` can pick out a particular place. Directories above the scan root
+/// are never matched. A trailing `/` restricts the pattern to directories.
+#[derive(Debug, Clone)]
+pub struct ExcludePattern {
+    matcher: GlobMatcher,
+    dirs_only: bool,
+}
+
+impl ExcludePattern {
+    /// Whether the pattern excludes `relative`, a path relative to the scan root, which
+    /// is a directory if `is_dir` is set.
+    pub fn matches(&self, relative: &Path, is_dir: bool) -> bool {
+        if self.dirs_only && !is_dir {
+            return false;
+        }
+        self.matcher.is_match(relative)
+            || relative
+                .file_name()
+                .is_some_and(|name| self.matcher.is_match(name))
+    }
+}
+
+impl FromStr for ExcludePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pattern = s.strip_suffix('/').unwrap_or(s);
+        if pattern.is_empty() {
+            return Err("empty exclude pattern".to_string());
+        }
+        let glob = Glob::new(pattern).map_err(|e| format!("invalid glob '{s}': {e}"))?;
+        Ok(ExcludePattern {
+            matcher: glob.compile_matcher(),
+            dirs_only: pattern.len() < s.len(),
+        })
+    }
 }
 
 /// Implements filtering logic for file paths based on size, extension, and regex criteria.
 impl FilterOptions {
-    /// Checks if the given `path` matches all filter options: size, extension, and regex.
+    /// Checks if the given `path` matches all filter options: exclusions, size, times,
+    /// ownership, extension, name and path patterns, content type, and the filter expression.
     ///
     /// # Arguments
     ///
@@ -21,91 +141,97 @@ impl FilterOptions {
     ///
     /// * `true` if the path matches all filter criteria, `false` otherwise.
     pub fn matches(&self, path: &Path) -> bool {
-        self.check_size(path) && self.check_extension(path) && self.check_regex(path)
+        self.matches_in(path, Path::new(""))
     }
 
-    /// Checks if the file at the given `path` matches the size constraints.
+    /// Like [`FilterOptions::matches`], but `path_regex`, `path_glob` and `path` in the
+    /// expression see `path` relative to `root`, the directory being scanned.
     ///
-    /// Returns `true` if the file size is within the specified minimum and maximum size (if set),
-    /// or if no size constraints are specified.
-    fn check_size(&self, path: &Path) -> bool {
-        if let Ok(metadata) = fs::metadata(path) {
-            if let Some(min) = self.min_size {
-                if metadata.len() < min {
-                    return false;
-                }
-            }
-            if let Some(max) = self.max_size {
-                if metadata.len() > max {
-                    return false;
-                }
-            }
-        }
-        true
+    /// This builds the filter with [`FilterOptions::to_expr`] on every call; to test many
+    /// files, build it once and call [`Expr::matches`] instead.
+    pub fn matches_in(&self, path: &Path, root: &Path) -> bool {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        self.to_expr()
+            .is_none_or(|expr| expr.matches(path, relative))
     }
 
-    /// Checks if the file at the given `path` matches the allowed extensions.
+    /// Builds every option, and `expression`, into a single [`Expr`] that all of them
+    /// must satisfy, or `None` if no option is set and every file matches.
     ///
-    /// Returns `true` if the file extension is in the allowed list (if set),
-    /// or if no extension constraints are specified.
-    fn check_extension(&self, path: &Path) -> bool {
-        if let Some(ref exts) = self.extensions {
-            if let Some(ext) = path.extension().and_then(OsStr::to_str) {
-                return exts.iter().any(|e| e.eq_ignore_ascii_case(ext));
-            } else {
-                return false; // No extension
-            }
-        }
-        true
-    }
+    /// Tests on size, times, ownership and content type accept files whose metadata or
+    /// content cannot be read, so the scan can report them instead of silently leaving
+    /// them out. Content is only read if every cheaper test passes.
+    pub fn to_expr(&self) -> Option<Expr> {
+        let lenient = |expr| Expr::Or(Box::new(expr), Box::new(Expr::Unreadable));
+        let mut tests = Vec::new();
 
-    /// Checks if the file name at the given `path` matches the specified regex pattern.
-    ///
-    /// Returns `true` if the file name matches the regex (if set),
-    /// or if no regex constraint is specified.
-    fn check_regex(&self, path: &Path) -> bool {
-        if let Some(ref re) = self.regex {
-            if let Some(name) = path.file_name().and_then(OsStr::to_str) {
-                return re.is_match(name);
-            } else {
-                return false; // No file name
-            }
-        }
-        true
-    }
-}
+        for pattern in &self.exclude {
+            tests.push(Expr::Not(Box::new(Expr::Excluded(pattern.clone()))));
+        }
+        if !self.exclude_extensions.is_empty() {
+            let excluded = TextTest::OneOf(self.exclude_extensions.clone());
+            tests.push(Expr::Not(Box::new(Expr::Text(TextField::Ext, excluded))));
+        }
+        if let Some(min) = self.min_size {
+            tests.push(lenient(Expr::Size(Compare::Ge, min)));
+        }
+        if let Some(max) = self.max_size {
+            tests.push(lenient(Expr::Size(Compare::Le, max)));
+        }
+        for filter in &self.times {
+            let (compare, time) = match filter.bound {
+                TimeBound::Before(time) => (Compare::Lt, time),
+                TimeBound::After(time) => (Compare::Gt, time),
+            };
+            tests.push(lenient(Expr::Time(filter.field, compare, time)));
+        }
+        if let Some(uid) = self.owner {
+            tests.push(lenient(Expr::Owner(Membership::In, vec![uid])));
+        }
+        if let Some(gid) = self.group {
+            tests.push(lenient(Expr::Group(Membership::In, vec![gid])));
+        }
+        if let Some(perm) = self.perm {
+            tests.push(lenient(Expr::Perm(perm)));
+        }
+        if let Some(extensions) = &self.extensions {
+            let allowed = TextTest::OneOf(extensions.clone());
+            tests.push(Expr::Text(TextField::Ext, allowed));
+        }
+        if let Some(re) = &self.regex {
+            tests.push(Expr::Text(TextField::Name, TextTest::Matches(re.clone())));
+        }
+        if let Some(re) = &self.path_regex {
+            tests.push(Expr::Text(TextField::Path, TextTest::Matches(re.clone())));
+        }
+        if let Some(glob) = &self.path_glob {
+            tests.push(Expr::Text(TextField::Path, TextTest::Glob(glob.clone())));
+        }
+        if let Some(expression) = &self.expression {
+            tests.push(expression.clone());
+        }
+        if !self.types.is_empty() {
+            tests.push(lenient(Expr::Kind(self.types.clone())));
+        }
+        if !self.mimes.is_empty() {
+            tests.push(lenient(Expr::Mime(self.mimes.clone())));
+        }
 
-/// Helper to parse filtering options from CLI args
-pub fn parse_filter_options(args: &[String]) -> FilterOptions {
-    let mut options = FilterOptions::default();
-
-    let mut i = 0;
-    while i < args.len() {
-        match args[i].as_str() {
-            "--min" => {
-                i += 1;
-                options.min_size = args.get(i).and_then(|v| v.parse::<u64>().ok());
-            }
-            "--max" => {
-                i += 1;
-                options.max_size = args.get(i).and_then(|v| v.parse::<u64>().ok());
-            }
-            "--ext" => {
-                i += 1;
-                options.extensions = args
-                    .get(i)
-                    .map(|v| v.split(',').map(|s| s.trim().to_string()).collect());
-            }
-            "--regex" => {
-                i += 1;
-                options.regex = args.get(i).and_then(|v| Regex::new(v).ok());
-            }
-            _ => {}
-        }
-        i += 1;
+        tests
+            .into_iter()
+            .reduce(|all, test| Expr::And(Box::new(all), Box::new(test)))
     }
 
-    options
+    /// Checks if the directory at `relative`, a path relative to the scan root, is
+    /// excluded by `exclude_dirs` or an `exclude` pattern. Scans skip such directories
+    /// without listing their contents.
+    pub fn excludes_dir(&self, relative: &Path) -> bool {
+        self.exclude_dirs.iter().any(|dir| relative.ends_with(dir))
+            || self
+                .exclude
+                .iter()
+                .any(|pattern| pattern.matches(relative, true))
+    }
 }
 
 /// Default implementation: match all files
@@ -116,6 +242,18 @@ impl Default for FilterOptions {
             max_size: None,
             extensions: None,
             regex: None,
+            path_regex: None,
+            path_glob: None,
+            exclude: Vec::new(),
+            exclude_dirs: Vec::new(),
+            exclude_extensions: Vec::new(),
+            times: Vec::new(),
+            owner: None,
+            group: None,
+            perm: None,
+            types: Vec::new(),
+            mimes: Vec::new(),
+            expression: None,
         }
     }
 }
@@ -1,30 +1,42 @@
 //! This module provides the main handler functions for the intelligent file deduplicator application.
 //!
-//! It re-exports core functionalities from submodules, including:
+//! Each handler receives the already-validated arguments of one subcommand from [`crate::cli`]
+//! and drives the library:
 //! - `compare_files`: For comparing files to detect duplicates.
-//! - `scan_directory_for_duplicates`: For scanning directories and identifying duplicate files.
+//! - `scan_directory`: For scanning directories and identifying duplicate files, with per-stage statistics.
 //! - `write_json_report`: For generating JSON reports of duplicate findings.
-//! - `parse_filter_options`: For parsing filter options to customize scanning behavior.
 //! - `delete_duplicates`: For safely deleting identified duplicate files.
 
-use crate::{
-    file_compare::compare_files, filter::parse_filter_options, report::write_json_report,
-    safe_delete::delete_duplicates, scanner::scan_directory_for_duplicates, ui::*,
+use crate::cli::{
+    ActionArgs, CacheAction, CacheArgs, CompareArgs, DeleteArgs, FindCopiesArgs, LinkArgs,
+    ReportArgs, RestoreArgs, RootArgs, ScanArgs, ScanOptionArgs,
+};
+use crate::ui::*;
+use intelligent_file_deduplicator::{
+    cache::HashCache,
+    content_type,
+    copies::find_copies,
+    error::DedupError,
+    file_compare::compare_files_with,
+    filter::FilterOptions,
+    hashing::HashAlgorithm,
+    journal::{Journal, default_journal_path, restore_journal},
+    report::write_json_report_with,
+    safe_delete::{DeleteMode, DeleteOptions, delete_duplicates_with},
+    scanner::{ScanOptions, ScanResult, ScanStats, SymlinkEntry, rehash_groups, scan_roots},
+    size::SizeFormat,
 };
 
-use std::process;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    sync::Arc,
+};
 
 /// Handles the `compare` command
-pub fn handle_compare_command(args: &[String]) {
-    if args.len() != 4 {
-        print_error("Error: compare requires 2 file paths.");
-        process::exit(1);
-    }
-
-    let file1 = &args[2];
-    let file2 = &args[3];
-
-    match compare_files(file1, file2) {
+pub fn handle_compare_command(args: &CompareArgs) {
+    match compare_files_with(&args.file1, &args.file2, args.algo) {
         Ok(true) => print_success("Files are identical."),
         Ok(false) => print_warning("Files are different."),
         Err(e) => print_error(&format!("Error comparing files: {e}")),
@@ -32,29 +44,34 @@ pub fn handle_compare_command(args: &[String]) {
 }
 
 /// Handles the `scan` command
-pub fn handle_scan_command(args: &[String]) {
-    if args.len() < 3 {
-        print_error("Error: scan requires a directory path.");
-        process::exit(1);
-    }
-
-    let dir = &args[2];
-    let filter_args = &args[3..];
-    let filters = parse_filter_options(filter_args);
-    print_info(&format!("📁 Scanning directory: {}", dir));
-    let duplicates = scan_directory_for_duplicates(dir, &filters);
+pub fn handle_scan_command(args: &ScanArgs, sizes: SizeFormat) {
+    let filters = args.scan.filter.options();
+    let options = root_options(scan_options(&args.scan), &args.roots, &args.dirs);
+    print_info(&format!(
+        "📁 Scanning {}: {} (algo = {})",
+        if args.dirs.len() == 1 {
+            "directory"
+        } else {
+            "directories"
+        },
+        list_dirs(&args.dirs),
+        options.algorithm
+    ));
+    let scan = run_scan(&args.dirs, &filters, &options, sizes);
+    print_hardlinks(&scan.hardlinks);
+    print_symlinks(&scan.symlinks);
 
-    if duplicates.is_empty() {
+    if scan.duplicates.is_empty() {
         print_success("No duplicates found.");
         return;
     }
 
     let mut found = false;
     println!("🔍 Duplicate files found:");
-    for (hash, files) in &duplicates {
+    for (hash, files) in &scan.duplicates {
         if files.len() > 1 {
             found = true;
-            println!("\n🧬 Hash: {}", hash);
+            print_group_header(hash, files, &scan, sizes);
             print_list(files);
         }
     }
@@ -65,83 +82,163 @@ pub fn handle_scan_command(args: &[String]) {
 }
 
 /// Handles the `report` command
-pub fn handle_report_command(args: &[String]) {
-    if args.len() < 4 {
-        print_error("Error: report requires a directory and an output file.");
-        process::exit(1);
-    }
-
-    let dir = &args[2];
-    let output_path = &args[3];
-    let filter_args = &args[4..];
-    let filters = parse_filter_options(filter_args);
-    print_info(&format!("📄 Generating report for: {}", dir));
-    let duplicates = scan_directory_for_duplicates(dir, &filters);
+pub fn handle_report_command(args: &ReportArgs, sizes: SizeFormat) {
+    let filters = args.scan.filter.options();
+    let options = root_options(scan_options(&args.scan), &args.roots, &args.dirs);
+    print_info(&format!(
+        "📄 Generating report for: {} (algo = {})",
+        list_dirs(&args.dirs),
+        options.algorithm
+    ));
+    let scan = run_scan(&args.dirs, &filters, &options, sizes);
 
-    match write_json_report(&duplicates, output_path) {
-        Ok(_) => print_success(&format!("Report saved to `{}`", output_path)),
+    match write_json_report_with(&scan.duplicates, scan.algorithm, sizes, &args.output) {
+        Ok(_) => print_success(&format!("Report saved to `{}`", args.output.display())),
         Err(e) => print_error(&format!("Failed to write report: {e}")),
     }
 }
 
 /// Handles the `delete` command
-pub fn handle_delete_command(args: &[String]) {
-    if args.len() < 3 {
-        print_error("Error: delete requires a directory.");
-        process::exit(1);
-    }
+pub fn handle_delete_command(args: &DeleteArgs, sizes: SizeFormat) {
+    handle_duplicates(
+        &args.dirs,
+        &args.roots,
+        &args.scan,
+        &args.action,
+        args.mode(),
+        sizes,
+    );
+}
 
-    let dir = &args[2];
-    let dry_run = args.contains(&"--dry-run".to_string());
-    let filter_args: Vec<String> = args.iter().skip(3).cloned().collect();
-    let filters = parse_filter_options(&filter_args);
+/// Handles the `link` command
+pub fn handle_link_command(args: &LinkArgs, sizes: SizeFormat) {
+    handle_duplicates(
+        &args.dirs,
+        &args.roots,
+        &args.scan,
+        &args.action,
+        args.mode(),
+        sizes,
+    );
+}
+
+/// Scans `dirs` and deletes or links the redundant copies of each duplicate group.
+fn handle_duplicates(
+    dirs: &[PathBuf],
+    roots: &RootArgs,
+    scan_args: &ScanOptionArgs,
+    action: &ActionArgs,
+    mode: DeleteMode,
+    sizes: SizeFormat,
+) {
+    let dry_run = action.dry_run;
+    let filters = scan_args.filter.options();
+    let options = root_options(scan_options(scan_args), roots, dirs);
 
+    let verb = match mode {
+        DeleteMode::Hardlink => "🔗 Linking",
+        DeleteMode::Reflink => "🪞 Cloning",
+        DeleteMode::Symlink { .. } => "↪️ Symlinking",
+        _ => "🧼 Deleting",
+    };
     print_info(&format!(
-        "🧼 Deleting duplicates in `{}` (dry-run = {})",
-        dir, dry_run
+        "{} duplicates in `{}` (dry-run = {}, algo = {})",
+        verb,
+        list_dirs(dirs),
+        dry_run,
+        options.algorithm
     ));
 
-    let duplicates = scan_directory_for_duplicates(dir, &filters);
+    let scan = run_scan(dirs, &filters, &options, sizes);
+    let mut duplicates = scan.duplicates;
+
+    // Never delete on the strength of a non-cryptographic hash alone
+    if !scan.algorithm.is_cryptographic() && !duplicates.is_empty() {
+        print_info(&format!(
+            "🔐 Confirming {} groups with {} before continuing",
+            duplicates.len(),
+            HashAlgorithm::Sha256
+        ));
+        let (confirmed, errors) = rehash_groups(&duplicates, HashAlgorithm::Sha256);
+        print_errors("could not be confirmed", &errors);
+        duplicates = confirmed;
+    }
 
     if duplicates.is_empty() {
-        print_success("No duplicates to delete.");
+        print_success(&format!("No duplicates to {}.", mode.verb()));
         return;
     }
 
-    match delete_duplicates(&duplicates, dry_run) {
-        Ok(_) => {
-            if dry_run {
-                print_success("Dry run complete. No files were deleted.");
-            } else {
-                print_success("Duplicate files deleted successfully.");
-            }
+    let mut delete_options = delete_options(action);
+    delete_options.mode = mode;
+    delete_options.only_under = action
+        .only_from(dirs)
+        .expect("--only-from is validated before any command runs");
+    delete_options.algorithm = if scan.algorithm.is_cryptographic() {
+        scan.algorithm
+    } else {
+        HashAlgorithm::Sha256
+    };
+    // Files behind unfollowed symlinks must survive, or the links would dangle
+    delete_options.protected = scan
+        .symlinks
+        .iter()
+        .filter_map(|symlink| symlink.target.clone())
+        .collect();
+
+    let report = match delete_duplicates_with(&duplicates, &delete_options) {
+        Ok(report) => report,
+        Err(e) => {
+            print_error(&format!("Error: {e}"));
+            process::exit(1);
+        }
+    };
+
+    let problems: Vec<&DedupError> = report
+        .problems()
+        .filter_map(|outcome| outcome.status.error())
+        .collect();
+    if let Some(e) = &report.aborted {
+        print_error(&format!("Stopped early: {e}"));
+    }
+    if report.is_complete() {
+        if dry_run {
+            print_success("Dry run complete. No files were changed.");
+        } else {
+            print_success("Duplicate files handled successfully.");
         }
-        Err(e) => print_error(&format!("Failed to delete duplicates: {e}")),
+        return;
+    }
+    let handled = report.outcomes.len() - problems.len();
+    print_warning(&format!(
+        "{} of {} files handled; {} skipped or failed:",
+        handled,
+        report.outcomes.len(),
+        problems.len()
+    ));
+    for error in problems {
+        println!("   - {}", error);
     }
+    process::exit(1);
 }
 
 /// Handles the `filter` command
-pub fn handle_filter_command(args: &[String]) {
-    if args.len() < 3 {
-        print_error("Error: filter requires a directory path.");
-        process::exit(1);
-    }
+pub fn handle_filter_command(args: &ScanArgs, sizes: SizeFormat) {
+    let filters = args.scan.filter.options();
+    let options = root_options(scan_options(&args.scan), &args.roots, &args.dirs);
+    let scan = run_scan(&args.dirs, &filters, &options, sizes);
 
-    let dir = &args[2];
-    let filters = parse_filter_options(&args[3..]);
-    let filtered = scan_directory_for_duplicates(dir, &filters);
-
-    if filtered.is_empty() {
+    if scan.duplicates.is_empty() {
         print_success("No matching files found.");
         return;
     }
 
     let mut found = false;
     println!("🔍 Matching files:");
-    for (hash, files) in &filtered {
+    for (hash, files) in &scan.duplicates {
         if files.len() > 1 {
             found = true;
-            println!("\n🧬 Hash: {}", hash);
+            print_group_header(hash, files, &scan, sizes);
             print_list(files);
         }
     }
@@ -150,3 +247,327 @@ pub fn handle_filter_command(args: &[String]) {
         print_success("No duplicate files matched the given filters.");
     }
 }
+
+/// Handles the `find-copies` command
+pub fn handle_find_copies_command(args: &FindCopiesArgs, sizes: SizeFormat) {
+    let filters = args.scan.filter.options();
+    let options = scan_options(&args.scan);
+    print_info(&format!(
+        "🧭 Looking for copies of {} files in: {} (algo = {})",
+        args.files.len(),
+        list_dirs(&args.dirs),
+        options.algorithm
+    ));
+    let result = find_copies(&args.files, &args.dirs, &filters, &options);
+    print_info(&format!(
+        "📊 {} files scanned → {} same size → {} copies",
+        result.files_scanned,
+        result.size_candidates,
+        result
+            .matches
+            .iter()
+            .map(|found| found.copies.len())
+            .sum::<usize>()
+    ));
+    print_errors("could not be read", &result.errors);
+    save_cache(&options);
+
+    for found in &result.matches {
+        println!(
+            "\n📄 {} ({})",
+            found.reference.display(),
+            sizes.format(found.size)
+        );
+        if found.copies.is_empty() && found.hardlinks.is_empty() {
+            println!("  No copies found.");
+        }
+        print_list(&found.copies);
+        for link in &found.hardlinks {
+            println!("🔗 {} (hardlink, same storage)", link.display());
+        }
+    }
+
+    // A reference that cannot be read was not searched for at all
+    if result.matches.len() < args.files.len() {
+        process::exit(1);
+    }
+}
+
+/// Handles the `cache` command (`cache stats` / `cache prune`)
+pub fn handle_cache_command(args: &CacheArgs, sizes: SizeFormat) {
+    let cache_path = cache_path(args.cache_file.as_deref());
+    let cache = match HashCache::load(&cache_path) {
+        Ok(cache) => cache,
+        Err(e) => {
+            print_error(&format!(
+                "Failed to open cache `{}`: {e}",
+                cache_path.display()
+            ));
+            process::exit(1);
+        }
+    };
+
+    match args.action {
+        CacheAction::Stats => {
+            let stats = cache.stats();
+            print_info(&format!("🗃️ Cache file: {}", cache.path().display()));
+            println!("  Entries: {}", stats.entries);
+            for (algorithm, count) in &stats.per_algorithm {
+                println!("    {algorithm}: {count}");
+            }
+            println!("  Size on disk: {}", sizes.format(stats.file_size));
+        }
+        CacheAction::Prune => {
+            let removed = cache.prune();
+            match cache.save() {
+                Ok(_) => print_success(&format!(
+                    "Pruned {removed} stale entries, {} remain.",
+                    cache.stats().entries
+                )),
+                Err(e) => print_error(&format!("Failed to save cache: {e}")),
+            }
+        }
+    }
+}
+
+/// Handles the `restore` command
+pub fn handle_restore_command(args: &RestoreArgs) {
+    let journal = match Journal::read(&args.journal) {
+        Ok(journal) => journal,
+        Err(e) => {
+            print_error(&format!(
+                "Failed to read journal `{}`: {e}",
+                args.journal.display()
+            ));
+            process::exit(1);
+        }
+    };
+    print_info(&format!(
+        "⏪ Restoring {} files from journal created {}",
+        journal.entries.len(),
+        journal.created
+    ));
+
+    let outcomes = restore_journal(&journal);
+    let mut failed = 0;
+    for outcome in &outcomes {
+        match &outcome.result {
+            Ok(_) => println!("♻️ Restored: {}", outcome.original.display()),
+            Err(e) => {
+                failed += 1;
+                eprintln!("❌ Could not restore {}: {}", outcome.original.display(), e);
+            }
+        }
+    }
+
+    if failed == 0 {
+        print_success(&format!("Restored {} files.", outcomes.len()));
+    } else {
+        print_error(&format!(
+            "Restored {} of {} files; {} failed.",
+            outcomes.len() - failed,
+            outcomes.len(),
+            failed
+        ));
+        process::exit(1);
+    }
+}
+
+/// Runs a scan of all `dirs`, prints its stage statistics and persists the hash cache.
+fn run_scan(
+    dirs: &[PathBuf],
+    filters: &FilterOptions,
+    options: &ScanOptions,
+    sizes: SizeFormat,
+) -> ScanResult {
+    let scan = scan_roots(dirs, filters, options);
+    print_scan_stats(&scan.stats, sizes);
+    print_errors("could not be scanned", &scan.errors);
+    save_cache(options);
+    scan
+}
+
+/// Persists the hash cache of `options`, if any, warning when it cannot be written.
+fn save_cache(options: &ScanOptions) {
+    if let Some(cache) = &options.cache
+        && let Err(e) = cache.save()
+    {
+        print_warning(&format!(
+            "Could not save hash cache `{}`: {e}",
+            cache.path().display()
+        ));
+    }
+}
+
+/// Prints how many files survived each stage of the scan pipeline.
+fn print_scan_stats(stats: &ScanStats, sizes: SizeFormat) {
+    print_info(&format!(
+        "📊 {} files scanned → {} same size → {} matching samples → {} duplicates in {} groups ({} cached hashes)",
+        stats.files_scanned,
+        stats.size_candidates,
+        stats.sample_candidates,
+        stats.duplicate_files,
+        stats.duplicate_groups,
+        stats.cache_hits
+    ));
+    if stats.hardlinked_files > 0 {
+        print_info(&format!(
+            "🔗 {} hardlinks already share storage and were not counted",
+            stats.hardlinked_files
+        ));
+    }
+    if stats.reclaimable_bytes > 0 {
+        print_info(&format!(
+            "💾 {} reclaimable",
+            sizes.format(stats.reclaimable_bytes)
+        ));
+    }
+}
+
+/// Prints the hash of a duplicate group with the size of each of its files, its detected
+/// content type and, when several directories were scanned, how many of them it spans.
+fn print_group_header(hash: &str, files: &[PathBuf], scan: &ScanResult, sizes: SizeFormat) {
+    let mut details = String::new();
+    if let Ok(Some(found)) = content_type::detect(&files[0]) {
+        details.push_str(&format!(", {}", found.mime));
+    }
+    if scan.roots.len() > 1 {
+        details.push_str(&format!(
+            ", in {} of {} roots",
+            scan.roots_of(files).len(),
+            scan.roots.len()
+        ));
+    }
+    match fs::metadata(&files[0]) {
+        Ok(metadata) => println!(
+            "\n🧬 Hash: {} ({} files × {}{})",
+            hash,
+            files.len(),
+            sizes.format(metadata.len()),
+            details
+        ),
+        Err(_) => println!("\n🧬 Hash: {}", hash),
+    }
+}
+
+/// Lists the scanned directories, e.g. "photos, backup".
+fn list_dirs(dirs: &[PathBuf]) -> String {
+    dirs.iter()
+        .map(|dir| dir.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Prints per-path errors as warnings, e.g. "2 paths could not be scanned".
+fn print_errors(what: &str, errors: &[DedupError]) {
+    if errors.is_empty() {
+        return;
+    }
+    print_warning(&format!("{} paths {}:", errors.len(), what));
+    for error in errors {
+        println!("   - {}", error);
+    }
+}
+
+/// Prints symlinks that were not followed, with the file each one points to.
+fn print_symlinks(symlinks: &[SymlinkEntry]) {
+    if symlinks.is_empty() {
+        return;
+    }
+    println!("↪️ Symlinks (not followed):");
+    for symlink in symlinks {
+        match &symlink.target {
+            Some(target) => println!("  {} -> {}", symlink.link.display(), target.display()),
+            None => println!("  {} -> (dangling)", symlink.link.display()),
+        }
+    }
+    println!();
+}
+
+/// Prints groups of paths that are hardlinks to the same file.
+fn print_hardlinks(hardlinks: &[Vec<PathBuf>]) {
+    if hardlinks.is_empty() {
+        return;
+    }
+    println!("🔗 Already linked (same inode, nothing to reclaim):");
+    for links in hardlinks {
+        println!();
+        print_list(links);
+    }
+    println!();
+}
+
+/// Resolves the cache file from `--cache-file`, falling back to the default location.
+fn cache_path(cache_file: Option<&Path>) -> PathBuf {
+    match cache_file {
+        Some(path) => path.to_path_buf(),
+        None => HashCache::default_path().unwrap_or_else(|| {
+            print_error("Error: cannot locate a cache directory; pass --cache-file.");
+            process::exit(1);
+        }),
+    }
+}
+
+/// Opens the hash cache unless `--no-cache` is given. Failing to open it only disables caching.
+fn open_cache(args: &ScanOptionArgs) -> Option<Arc<HashCache>> {
+    if args.no_cache {
+        return None;
+    }
+    let path = cache_path(args.cache_file.as_deref());
+    match HashCache::load(&path) {
+        Ok(cache) => Some(Arc::new(cache)),
+        Err(e) => {
+            print_warning(&format!(
+                "Hash cache `{}` unavailable, continuing without it: {e}",
+                path.display()
+            ));
+            None
+        }
+    }
+}
+
+/// Builds the scan options shared by every command that scans directories.
+fn scan_options(args: &ScanOptionArgs) -> ScanOptions {
+    ScanOptions {
+        algorithm: args.algo,
+        cache: open_cache(args),
+        symlinks: args.symlink_policy(),
+        respect_ignore: args.respect_ignore,
+        ..ScanOptions::default()
+    }
+}
+
+/// Adds `--cross-root` and `--present-in` to `options` for a scan of `dirs`.
+fn root_options(options: ScanOptions, roots: &RootArgs, dirs: &[PathBuf]) -> ScanOptions {
+    ScanOptions {
+        cross_root: roots.cross_root,
+        present_in: roots
+            .present_in(dirs)
+            .expect("--present-in is validated before any command runs"),
+        ..options
+    }
+}
+
+/// Builds the delete options from `--dry-run`, `--no-verify`, `--keep` and `--journal`.
+fn delete_options(args: &ActionArgs) -> DeleteOptions {
+    let verify = !args.no_verify;
+    if !verify {
+        print_warning("Byte-for-byte verification disabled (--no-verify).");
+    }
+
+    let keep = args.keep.clone().unwrap_or_default();
+    print_info(&format!("📌 Keep policy: {keep}"));
+
+    let journal = args.journal.clone().or_else(default_journal_path);
+    if journal.is_none() && !args.dry_run {
+        print_warning("No journal location found; this run cannot be undone with `restore`.");
+    }
+
+    DeleteOptions {
+        dry_run: args.dry_run,
+        verify,
+        keep,
+        journal,
+        ..DeleteOptions::default()
+    }
+}
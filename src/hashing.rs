@@ -1,26 +1,159 @@
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::{
+    fmt,
     fs::File,
-    io::{self, Read},
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+    str::FromStr,
 };
+use xxhash_rust::xxh3::Xxh3;
+
+/// Size of the read buffer used when streaming file contents into a hasher.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// A streaming hasher that turns file contents into a hexadecimal digest.
+///
+/// Implementations wrap a concrete hash function so the scanner and comparison
+/// code can stay agnostic of the algorithm chosen on the command line.
+pub trait ContentHasher {
+    /// Feeds a chunk of data into the hasher.
+    fn update(&mut self, data: &[u8]);
+
+    /// Consumes the hasher and returns the digest as a lowercase hexadecimal string.
+    fn finalize(self: Box<Self>) -> String;
+}
+
+impl ContentHasher for Sha256 {
+    fn update(&mut self, data: &[u8]) {
+        Digest::update(self, data);
+    }
+
+    fn finalize(self: Box<Self>) -> String {
+        format!("{:x}", Digest::finalize(*self))
+    }
+}
+
+impl ContentHasher for blake3::Hasher {
+    fn update(&mut self, data: &[u8]) {
+        blake3::Hasher::update(self, data);
+    }
+
+    fn finalize(self: Box<Self>) -> String {
+        blake3::Hasher::finalize(&self).to_hex().to_string()
+    }
+}
+
+impl ContentHasher for Xxh3 {
+    fn update(&mut self, data: &[u8]) {
+        Xxh3::update(self, data);
+    }
+
+    fn finalize(self: Box<Self>) -> String {
+        format!("{:032x}", self.digest128())
+    }
+}
+
+/// Hash algorithms available for content comparison.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    /// SHA-256: cryptographic, the default.
+    #[default]
+    Sha256,
+    /// BLAKE3: cryptographic and considerably faster than SHA-256.
+    Blake3,
+    /// XXH3 (128-bit): non-cryptographic, intended for quick triage passes.
+    Xxh3,
+}
+
+impl HashAlgorithm {
+    /// All supported algorithms, in the order they are listed in help output.
+    pub const ALL: [HashAlgorithm; 3] = [
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Blake3,
+        HashAlgorithm::Xxh3,
+    ];
+
+    /// Creates a fresh hasher for this algorithm.
+    pub fn hasher(self) -> Box<dyn ContentHasher> {
+        match self {
+            HashAlgorithm::Sha256 => Box::new(Sha256::new()),
+            HashAlgorithm::Blake3 => Box::new(blake3::Hasher::new()),
+            HashAlgorithm::Xxh3 => Box::new(Xxh3::new()),
+        }
+    }
+
+    /// The name used on the command line and in reports.
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+            HashAlgorithm::Xxh3 => "xxh3",
+        }
+    }
+
+    /// Whether collisions are computationally infeasible, making the hash safe to act on.
+    pub fn is_cryptographic(self) -> bool {
+        !matches!(self, HashAlgorithm::Xxh3)
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        HashAlgorithm::ALL
+            .into_iter()
+            .find(|algo| algo.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<&str> = HashAlgorithm::ALL.iter().map(|a| a.name()).collect();
+                format!(
+                    "unknown hash algorithm '{}' (expected one of: {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
 
 /// Computes the SHA-256 hash of the file at the given path.
 ///
 /// # Arguments
 ///
-/// * `path` - A string slice that holds the path to the file to be hashed.
+/// * `path` - The path to the file to be hashed; any `Path`, `PathBuf`, `OsStr` or `str`.
 ///
 /// # Returns
 ///
 /// * `io::Result<String>` - On success, returns the hexadecimal string representation of the file's SHA-256 hash.
 ///   On failure, returns an `io::Error`.
-pub fn hash_file(path: &str) -> io::Result<String> {
+pub fn hash_file<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    hash_file_with(path, HashAlgorithm::Sha256)
+}
+
+/// Computes the hash of the file at the given path using the chosen algorithm.
+///
+/// # Arguments
+///
+/// * `path` - The file to hash.
+/// * `algorithm` - The [`HashAlgorithm`] to use.
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The hexadecimal digest of the file's contents.
+pub fn hash_file_with<P: AsRef<Path>>(path: P, algorithm: HashAlgorithm) -> io::Result<String> {
     // Open the file at the specified path
     let mut file = File::open(path)?;
-    // Create a new SHA-256 hasher instance
-    let mut hasher = Sha256::new();
+    // Create a new hasher instance for the chosen algorithm
+    let mut hasher = algorithm.hasher();
     // Buffer to read file chunks
-    let mut buffer = [0u8; 1024];
+    let mut buffer = vec![0u8; BUFFER_SIZE];
 
     // Read the file in chunks and update the hasher
     loop {
@@ -32,5 +165,47 @@ pub fn hash_file(path: &str) -> io::Result<String> {
     }
 
     // Finalize the hash and return it as a hexadecimal string
-    Ok(format!("{:x}", hasher.finalize()))
+    Ok(hasher.finalize())
+}
+
+/// Computes a hash over the first and last `sample_size` bytes of a file.
+///
+/// This is much cheaper than [`hash_file`] for large files and is used by the scanner
+/// to rule out same-size files that differ near their start or end. Two files with
+/// different sample hashes are guaranteed to differ; equal sample hashes still need
+/// a full hash to confirm.
+///
+/// # Arguments
+///
+/// * `path` - The file to sample.
+/// * `sample_size` - Number of bytes read from each end of the file.
+/// * `algorithm` - The [`HashAlgorithm`] to use.
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The hexadecimal digest of the sampled bytes.
+pub fn hash_file_sample<P: AsRef<Path>>(
+    path: P,
+    sample_size: u64,
+    algorithm: HashAlgorithm,
+) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut hasher = algorithm.hasher();
+    let mut buffer = Vec::new();
+
+    // Head of the file
+    (&mut file).take(sample_size).read_to_end(&mut buffer)?;
+    hasher.update(&buffer);
+
+    // Tail of the file, skipped when the head already covered it
+    if len > sample_size {
+        let tail_start = len.saturating_sub(sample_size).max(sample_size);
+        file.seek(SeekFrom::Start(tail_start))?;
+        buffer.clear();
+        file.take(sample_size).read_to_end(&mut buffer)?;
+        hasher.update(&buffer);
+    }
+
+    Ok(hasher.finalize())
 }
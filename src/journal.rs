@@ -0,0 +1,419 @@
+//! Undo journals recording what a `delete` run did to each file.
+//!
+//! Every file a `delete` run removes or moves is written to a JSON Lines journal together
+//! with the copy that was kept, its hash and its original metadata. [`restore_journal`]
+//! replays a journal backwards: moved files are moved back, removed or linked files are
+//! recreated from the kept copy, and reflinked files get their own copy of their data
+//! again, all with their permissions and timestamps restored.
+
+use crate::hashing::{HashAlgorithm, hash_file_with};
+use crate::link::{replace_atomically, same_file};
+use crate::trash::move_file;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    fs::{self, File, FileTimes, Metadata},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{self, Path, PathBuf},
+    time::SystemTime,
+};
+
+/// What happened to a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalAction {
+    /// Unlinked permanently; restored by copying the kept file.
+    Removed,
+    /// Moved into the freedesktop.org trash.
+    Trashed,
+    /// Moved into a quarantine directory.
+    Quarantined,
+    /// Replaced by a hardlink to the kept file; restored by copying the kept file back
+    /// over the link.
+    Hardlinked,
+    /// Replaced by a symbolic link to the kept file; restored by copying the kept file
+    /// back over the link.
+    Symlinked,
+    /// Made to share extents with the kept file; restored by rewriting it with its own,
+    /// unshared copy of the data.
+    Reflinked,
+}
+
+/// A single file handled during a `delete` run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub action: JournalAction,
+    /// Where the file lived before the run.
+    #[serde(with = "crate::paths::serde_path")]
+    pub original: PathBuf,
+    /// Where the file was moved to, or `None` if it was removed.
+    #[serde(with = "crate::paths::serde_path_option")]
+    pub moved_to: Option<PathBuf>,
+    /// The duplicate that was kept in its place.
+    #[serde(with = "crate::paths::serde_path")]
+    pub kept: PathBuf,
+    /// Content hash shared by `original` and `kept`.
+    pub hash: String,
+    /// Size of the file in bytes.
+    pub size: u64,
+    /// Unix permission bits, where available.
+    pub mode: Option<u32>,
+    /// Last modification time.
+    pub modified: Option<SystemTime>,
+    /// Last access time.
+    pub accessed: Option<SystemTime>,
+}
+
+impl JournalEntry {
+    /// Creates an entry for `original`, capturing its metadata before it is touched.
+    ///
+    /// Both paths are made absolute, so the journal can be restored from any directory.
+    pub fn new(
+        action: JournalAction,
+        original: &Path,
+        kept: &Path,
+        hash: &str,
+        metadata: &Metadata,
+    ) -> Self {
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(metadata.permissions().mode())
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        JournalEntry {
+            action,
+            original: absolute(original),
+            moved_to: None,
+            kept: absolute(kept),
+            hash: hash.to_string(),
+            size: metadata.len(),
+            mode,
+            modified: metadata.modified().ok(),
+            accessed: metadata.accessed().ok(),
+        }
+    }
+}
+
+/// `path` made absolute against the current directory, or as given if that fails.
+pub(crate) fn absolute(path: &Path) -> PathBuf {
+    path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// The result of restoring one journal entry.
+#[derive(Debug)]
+pub struct RestoreOutcome {
+    /// The path that was (or could not be) restored.
+    pub original: PathBuf,
+    pub result: io::Result<()>,
+}
+
+/// The journal of one `delete` run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Journal {
+    /// Local time the run started, in RFC 3339 format.
+    pub created: String,
+    /// The algorithm that produced the `hash` of each entry.
+    pub algorithm: HashAlgorithm,
+    /// Actions in the order they were performed.
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// Reads a journal written by a [`JournalWriter`].
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let mut lines = BufReader::new(file).lines();
+        let header: JournalHeader = match lines.next() {
+            Some(line) => serde_json::from_str(&line?).map_err(io::Error::other)?,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the journal is empty",
+                ));
+            }
+        };
+        let mut entries = Vec::new();
+        for line in lines {
+            let line = line?;
+            if !line.trim().is_empty() {
+                entries.push(serde_json::from_str(&line).map_err(io::Error::other)?);
+            }
+        }
+        Ok(Journal {
+            created: header.created,
+            algorithm: header.algorithm,
+            entries,
+        })
+    }
+}
+
+/// The first line of a journal file.
+#[derive(Serialize, Deserialize)]
+struct JournalHeader {
+    created: String,
+    algorithm: HashAlgorithm,
+}
+
+/// A journal file written while a `delete` run is in progress.
+///
+/// The file holds one JSON object per line: a header with the creation time and hash
+/// algorithm, then one [`JournalEntry`] per action. Each entry is appended and synced to
+/// disk as soon as its action has run, so an interrupted run leaves a journal covering
+/// everything it did.
+#[derive(Debug)]
+pub struct JournalWriter {
+    path: PathBuf,
+    file: File,
+    entries: usize,
+}
+
+impl JournalWriter {
+    /// Creates the journal file at `path`, creating parent directories as needed, and
+    /// writes its header. Call this before touching any file, so a journal that cannot
+    /// be written stops the run before anything is lost. An existing file is never
+    /// overwritten, since it may hold the only record of an earlier run.
+    pub fn create<P: AsRef<Path>>(path: P, algorithm: HashAlgorithm) -> io::Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let mut writer = JournalWriter {
+            path: path.to_path_buf(),
+            file: File::options()
+                .write(true)
+                .create_new(true)
+                .open(path)
+                .map_err(|e| match e.kind() {
+                    io::ErrorKind::AlreadyExists => io::Error::new(
+                        e.kind(),
+                        "a file already exists there; choose a new journal path",
+                    ),
+                    _ => e,
+                })?,
+            entries: 0,
+        };
+        let header = JournalHeader {
+            created: Local::now().to_rfc3339(),
+            algorithm,
+        };
+        writer.write_line(&header)?;
+        Ok(writer)
+    }
+
+    /// Appends `entry` and syncs it to disk.
+    pub fn append(&mut self, entry: &JournalEntry) -> io::Result<()> {
+        self.write_line(entry)?;
+        self.entries += 1;
+        Ok(())
+    }
+
+    fn write_line<T: Serialize>(&mut self, value: &T) -> io::Result<()> {
+        let mut line = serde_json::to_vec(value).map_err(io::Error::other)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.file.sync_data()
+    }
+
+    /// Where the journal is being written.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// How many entries have been written.
+    pub fn len(&self) -> usize {
+        self.entries
+    }
+
+    /// Whether no entries have been written yet.
+    pub fn is_empty(&self) -> bool {
+        self.entries == 0
+    }
+}
+
+/// Returns a fresh, timestamped journal path inside `$XDG_DATA_HOME/hashlaser/journals`
+/// (falling back to `$HOME/.local/share/hashlaser/journals`).
+pub fn default_journal_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+    let name = format!("journal-{}.jsonl", Local::now().format("%Y%m%dT%H%M%S%.3f"));
+    Some(base.join("hashlaser").join("journals").join(name))
+}
+
+/// Undoes every entry of `journal`, most recent first.
+///
+/// Files that were moved are moved back (removing their `.trashinfo` when they came
+/// from the trash). Files that were removed are recreated by copying the kept file,
+/// after checking that it still has the recorded hash. In all cases the recorded
+/// permissions and timestamps are reapplied. An entry is never restored over an
+/// existing file, except that a hardlink or symbolic link to the kept file is atomically
+/// replaced by an independent copy of it, and a reflinked file is atomically replaced by a
+/// copy of itself whose data no longer shares extents with the kept file.
+///
+/// # Returns
+///
+/// One [`RestoreOutcome`] per entry, in the order they were processed.
+pub fn restore_journal(journal: &Journal) -> Vec<RestoreOutcome> {
+    journal
+        .entries
+        .iter()
+        .rev()
+        .map(|entry| RestoreOutcome {
+            original: entry.original.clone(),
+            result: restore_entry(entry, journal.algorithm),
+        })
+        .collect()
+}
+
+fn restore_entry(entry: &JournalEntry, algorithm: HashAlgorithm) -> io::Result<()> {
+    let original = entry.original.as_path();
+    match entry.action {
+        JournalAction::Hardlinked | JournalAction::Symlinked => {
+            return restore_linked(original, entry, algorithm);
+        }
+        JournalAction::Reflinked => return restore_reflinked(original, entry, algorithm),
+        _ => {}
+    }
+    if original.symlink_metadata().is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "a file already exists at the original path",
+        ));
+    }
+    if let Some(parent) = original.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    match (&entry.action, &entry.moved_to) {
+        (JournalAction::Removed, _) => {
+            check_kept(entry, algorithm)?;
+            fs::copy(&entry.kept, original)?;
+        }
+        (action, Some(moved_to)) => {
+            let moved_to = moved_to.as_path();
+            move_file(moved_to, original)?;
+            if *action == JournalAction::Trashed {
+                remove_trash_info(moved_to);
+            }
+        }
+        (_, None) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "journal entry has no destination to restore from",
+            ));
+        }
+    }
+
+    restore_metadata(original, entry)
+}
+
+/// Swaps a hardlink or symbolic link back for an independent copy of the kept file.
+fn restore_linked(
+    original: &Path,
+    entry: &JournalEntry,
+    algorithm: HashAlgorithm,
+) -> io::Result<()> {
+    let kept = entry.kept.as_path();
+    let still_linked = if entry.action == JournalAction::Symlinked {
+        original.symlink_metadata()?.file_type().is_symlink()
+            && fs::canonicalize(original)? == fs::canonicalize(kept)?
+    } else {
+        same_file(original, kept)?
+    };
+    if !still_linked {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "the original path no longer links to the kept file",
+        ));
+    }
+    check_kept(entry, algorithm)?;
+    replace_atomically(original, |temp| {
+        fs::copy(&entry.kept, temp)?;
+        restore_metadata(temp, entry)
+    })
+}
+
+/// Rewrites a reflinked file with a private copy of its data.
+fn restore_reflinked(
+    original: &Path,
+    entry: &JournalEntry,
+    algorithm: HashAlgorithm,
+) -> io::Result<()> {
+    if hash_file_with(original, algorithm)? != entry.hash {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the file has changed since it was cloned",
+        ));
+    }
+    replace_atomically(original, |temp| {
+        // Copied by hand: fs::copy may clone the extents again
+        let mut source = File::open(original)?;
+        let mut target = File::options().write(true).create_new(true).open(temp)?;
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = source.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            target.write_all(&buffer[..read])?;
+        }
+        drop(target);
+        restore_metadata(temp, entry)
+    })
+}
+
+/// Fails unless the kept file still has the hash recorded in the journal.
+fn check_kept(entry: &JournalEntry, algorithm: HashAlgorithm) -> io::Result<()> {
+    if hash_file_with(&entry.kept, algorithm)? != entry.hash {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "kept file {} has changed since deletion",
+                entry.kept.display()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Deletes the `.trashinfo` file belonging to a file in `<trash>/files/`.
+fn remove_trash_info(trashed: &Path) {
+    if let (Some(files_dir), Some(name)) = (trashed.parent(), trashed.file_name())
+        && let Some(trash_dir) = files_dir.parent()
+    {
+        let mut info_name = name.to_os_string();
+        info_name.push(".trashinfo");
+        let _ = fs::remove_file(trash_dir.join("info").join(info_name));
+    }
+}
+
+/// Reapplies the recorded timestamps and permissions to a restored file.
+fn restore_metadata(path: &Path, entry: &JournalEntry) -> io::Result<()> {
+    let mut times = FileTimes::new();
+    if let Some(modified) = entry.modified {
+        times = times.set_modified(modified);
+    }
+    if let Some(accessed) = entry.accessed {
+        times = times.set_accessed(accessed);
+    }
+    // The owner may set times through any handle, so a read-only file needs no write
+    // access; only a file without read permission has to be opened for writing
+    File::open(path)
+        .or_else(|_| File::options().write(true).open(path))?
+        .set_times(times)?;
+
+    #[cfg(unix)]
+    if let Some(mode) = entry.mode {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    }
+
+    Ok(())
+}
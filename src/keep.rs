@@ -0,0 +1,181 @@
+//! Rules for choosing which file of a duplicate group survives.
+//!
+//! A [`KeepPolicy`] is an ordered list of [`KeepRule`]s. The first rule decides which
+//! file is kept; later rules only break ties left by the earlier ones. Remaining ties
+//! go to the file listed first, which for scanner groups is the alphabetically first.
+
+use std::{
+    cmp::Ordering,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::SystemTime,
+};
+
+/// A single criterion for preferring one duplicate over another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeepRule {
+    /// Keep the file with the oldest modification time.
+    Oldest,
+    /// Keep the file with the newest modification time.
+    Newest,
+    /// Keep the file with the shortest path.
+    Shortest,
+    /// Keep the file with the longest path.
+    Longest,
+    /// Keep the file whose path sorts first.
+    Alphabetical,
+    /// Keep a file located inside the given directory.
+    Prefer(PathBuf),
+}
+
+impl FromStr for KeepRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(dir) = s.strip_prefix("prefer:") {
+            if dir.is_empty() {
+                return Err("keep rule 'prefer:' needs a directory".to_string());
+            }
+            return Ok(KeepRule::Prefer(PathBuf::from(dir)));
+        }
+        match s.to_ascii_lowercase().as_str() {
+            "oldest" => Ok(KeepRule::Oldest),
+            "newest" => Ok(KeepRule::Newest),
+            "shortest" => Ok(KeepRule::Shortest),
+            "longest" => Ok(KeepRule::Longest),
+            "alpha" | "alphabetical" => Ok(KeepRule::Alphabetical),
+            _ => Err(format!(
+                "unknown keep rule '{}' (expected oldest, newest, shortest, longest, alpha or prefer:<dir>)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for KeepRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeepRule::Oldest => f.write_str("oldest"),
+            KeepRule::Newest => f.write_str("newest"),
+            KeepRule::Shortest => f.write_str("shortest"),
+            KeepRule::Longest => f.write_str("longest"),
+            KeepRule::Alphabetical => f.write_str("alpha"),
+            KeepRule::Prefer(dir) => write!(f, "prefer:{}", dir.display()),
+        }
+    }
+}
+
+/// An ordered list of keep rules, each breaking ties left by the previous ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeepPolicy {
+    pub rules: Vec<KeepRule>,
+}
+
+impl FromStr for KeepPolicy {
+    type Err = String;
+
+    /// Parses a comma-separated rule list such as `prefer:/mnt/photos,oldest,shortest`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rules = s
+            .split(',')
+            .map(str::trim)
+            .filter(|rule| !rule.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(KeepPolicy { rules })
+    }
+}
+
+impl fmt::Display for KeepPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.rules.is_empty() {
+            return f.write_str("first");
+        }
+        let rules: Vec<String> = self.rules.iter().map(ToString::to_string).collect();
+        f.write_str(&rules.join(","))
+    }
+}
+
+/// The facts about one file that rules compare on.
+struct Candidate<'a> {
+    path: &'a Path,
+    modified: Option<SystemTime>,
+}
+
+impl KeepPolicy {
+    /// Chooses the file to keep among `files`, considering only the indices in `eligible`.
+    ///
+    /// # Returns
+    ///
+    /// The index into `files` of the file to keep, or `None` if `eligible` is empty.
+    /// Ties are resolved in favour of the earliest index in `eligible`.
+    pub fn choose(&self, files: &[PathBuf], eligible: &[usize]) -> Option<usize> {
+        let needs_mtime = self
+            .rules
+            .iter()
+            .any(|rule| matches!(rule, KeepRule::Oldest | KeepRule::Newest));
+        let candidates: Vec<(usize, Candidate)> = eligible
+            .iter()
+            .map(|&index| {
+                let path = files[index].as_path();
+                let modified = needs_mtime
+                    .then(|| fs::metadata(path).and_then(|m| m.modified()).ok())
+                    .flatten();
+                (index, Candidate { path, modified })
+            })
+            .collect();
+
+        candidates
+            .into_iter()
+            .min_by(|(_, a), (_, b)| self.compare(a, b))
+            .map(|(index, _)| index)
+    }
+
+    /// Orders two candidates; `Ordering::Less` means `a` is the better file to keep.
+    fn compare(&self, a: &Candidate, b: &Candidate) -> Ordering {
+        self.rules
+            .iter()
+            .map(|rule| compare_by_rule(rule, a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+fn compare_by_rule(rule: &KeepRule, a: &Candidate, b: &Candidate) -> Ordering {
+    match rule {
+        KeepRule::Oldest => compare_times(a.modified, b.modified, |x, y| x.cmp(&y)),
+        KeepRule::Newest => compare_times(a.modified, b.modified, |x, y| y.cmp(&x)),
+        KeepRule::Shortest => path_len(a.path).cmp(&path_len(b.path)),
+        KeepRule::Longest => path_len(b.path).cmp(&path_len(a.path)),
+        KeepRule::Alphabetical => a.path.cmp(b.path),
+        KeepRule::Prefer(dir) => is_inside(b.path, dir).cmp(&is_inside(a.path, dir)),
+    }
+}
+
+/// Compares optional timestamps; files with an unknown time lose against any known time.
+fn compare_times(
+    a: Option<SystemTime>,
+    b: Option<SystemTime>,
+    order: impl Fn(SystemTime, SystemTime) -> Ordering,
+) -> Ordering {
+    match (a, b) {
+        (Some(x), Some(y)) => order(x, y),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Length of a path in bytes, as shown to the user.
+fn path_len(path: &Path) -> usize {
+    path.as_os_str().len()
+}
+
+/// Checks whether `path` lies inside `dir`, comparing canonical paths where possible.
+fn is_inside(path: &Path, dir: &Path) -> bool {
+    match (fs::canonicalize(path), fs::canonicalize(dir)) {
+        (Ok(path), Ok(dir)) => path.starts_with(dir),
+        _ => path.starts_with(dir),
+    }
+}
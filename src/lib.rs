@@ -6,16 +6,44 @@
 //! ## Modules
 //!
 //! - [`hashing`]: Utilities for hashing files to identify duplicates efficiently.
+//! - [`cache`]: A persistent hash cache that lets repeated scans skip unchanged files.
+//! - [`content_type`]: Content types detected from magic bytes, for `--type` and `--mime`.
+//! - [`copies`]: Finding every copy of given files without a full duplicate scan.
+//! - [`error`]: The crate-wide [`error::DedupError`] type for per-path failures.
+//! - [`expr`]: The `--where` filter expression language and its predicate tree.
 //! - [`file_compare`]: Functions for comparing files at a binary or content level.
 //! - [`scanner`]: Tools for scanning directories and collecting file metadata.
 //! - [`report`]: Facilities for generating reports on duplicates and actions taken.
 //! - [`filter`]: Mechanisms for filtering files based on user-defined criteria.
+//! - [`keep`]: Rules for choosing which file of a duplicate group is kept.
+//! - [`link`]: Replacing duplicates with links to the kept copy.
+//! - [`ownership`]: Owner, group and permission filters, and the check that a file can be deleted.
+//! - [`paths`]: Lossless storage of paths that are not valid UTF-8.
+//! - [`reflink`]: Copy-on-write clones that share extents without sharing metadata.
+//! - [`size`]: Parsing and printing byte sizes in human units such as `10MiB`.
 //! - [`safe_delete`]: Safe deletion utilities to remove duplicates without data loss.
+//! - [`timestamp`]: Points in time given as relative durations or absolute dates.
+//! - [`trash`]: Trash and quarantine moves as recoverable alternatives to deletion.
+//! - [`journal`]: Manifests recording what a delete run did, so it can be undone.
 //!
 //! Each module is documented individually with further details and usage examples.
+pub mod cache;
+pub mod content_type;
+pub mod copies;
+pub mod error;
+pub mod expr;
 pub mod file_compare;
 pub mod filter;
 pub mod hashing;
+pub mod journal;
+pub mod keep;
+pub mod link;
+pub mod ownership;
+pub mod paths;
+pub mod reflink;
 pub mod report;
 pub mod safe_delete;
 pub mod scanner;
+pub mod size;
+pub mod timestamp;
+pub mod trash;
@@ -0,0 +1,203 @@
+//! Replacing duplicates with links to the kept copy instead of deleting them.
+//!
+//! A replacement is always built under a temporary name in the duplicate's own directory
+//! and then renamed over it, so the duplicate's path refers either to the original file
+//! or to the finished link, never to nothing.
+
+use std::{
+    fs::{self, Metadata},
+    io,
+    path::{Component, Path, PathBuf},
+    process,
+};
+
+/// Replaces `path` with a hardlink to `keep`.
+///
+/// Hardlinks cannot span filesystems, so this fails with
+/// [`io::ErrorKind::CrossesDevices`] when `path` and `keep` live on different ones.
+/// Afterwards both paths share one inode, and with it the permissions, owner and
+/// timestamps of `keep`; use [`metadata_differences`] beforehand to find out what changes.
+pub fn replace_with_hardlink(path: &Path, keep: &Path) -> io::Result<()> {
+    if !same_filesystem(path, keep)? {
+        return Err(io::Error::new(
+            io::ErrorKind::CrossesDevices,
+            format!("{} is on a different filesystem", keep.display()),
+        ));
+    }
+    replace_atomically(path, |temp| fs::hard_link(keep, temp))
+}
+
+/// Replaces `path` with a symbolic link to `keep` and returns the link target.
+///
+/// The target is computed by [`symlink_target`]. Before the link is renamed over
+/// `path`, it is checked to resolve to `keep`, so a miscomputed target can never
+/// replace a file.
+#[cfg(unix)]
+pub fn replace_with_symlink(path: &Path, keep: &Path, relative: bool) -> io::Result<PathBuf> {
+    let target = symlink_target(path, keep, relative)?;
+    let kept = fs::canonicalize(keep)?;
+    replace_atomically(path, |temp| {
+        std::os::unix::fs::symlink(&target, temp)?;
+        if fs::canonicalize(temp)? != kept {
+            return Err(io::Error::other(format!(
+                "link target {} does not resolve to {}",
+                target.display(),
+                keep.display()
+            )));
+        }
+        Ok(())
+    })?;
+    Ok(target)
+}
+
+/// Symbolic links are only created on Unix.
+#[cfg(not(unix))]
+pub fn replace_with_symlink(_path: &Path, _keep: &Path, _relative: bool) -> io::Result<PathBuf> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "symbolic links are not supported on this platform",
+    ))
+}
+
+/// The target a symbolic link at `path` needs to point at `keep`.
+///
+/// Absolute targets are the canonical path of `keep`. Relative targets are computed
+/// from the canonical directory of `path`, climbing with `..` as needed, so
+/// `photos/2023/a.jpg` linking to `photos/2024/a.jpg` gets `../2024/a.jpg`.
+pub fn symlink_target(path: &Path, keep: &Path, relative: bool) -> io::Result<PathBuf> {
+    let kept = fs::canonicalize(keep)?;
+    if !relative {
+        return Ok(kept);
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Ok(relative_path(&fs::canonicalize(parent)?, &kept))
+}
+
+/// Expresses the absolute path `to` relative to the absolute directory `from`.
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..from.len() {
+        relative.push("..");
+    }
+    for component in &to[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
+/// Atomically replaces `path` with a file created by `create`.
+///
+/// `create` receives an unused path in the same directory as `path`. If it succeeds, the
+/// new file is renamed over `path`; if it or the rename fails, the temporary file is
+/// removed and `path` is left untouched.
+pub fn replace_atomically(path: &Path, create: impl Fn(&Path) -> io::Result<()>) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+
+    let mut counter = 0;
+    let temp = loop {
+        let mut name = std::ffi::OsString::from(".");
+        name.push(file_name);
+        name.push(format!(".hashlaser-{}-{}", process::id(), counter));
+        let temp = path.with_file_name(name);
+        if temp.symlink_metadata().is_ok() {
+            counter += 1;
+            continue;
+        }
+        match create(&temp) {
+            Ok(()) => break temp,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => counter += 1,
+            Err(e) => {
+                let _ = fs::remove_file(&temp);
+                return Err(e);
+            }
+        }
+    };
+
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+/// Checks whether two paths live on the same filesystem.
+#[cfg(unix)]
+pub fn same_filesystem(a: &Path, b: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(fs::metadata(a)?.dev() == fs::metadata(b)?.dev())
+}
+
+/// Without device numbers, assume a shared filesystem and let linking report otherwise.
+#[cfg(not(unix))]
+pub fn same_filesystem(_a: &Path, _b: &Path) -> io::Result<bool> {
+    Ok(true)
+}
+
+/// Checks whether two paths are hardlinks to the same file.
+#[cfg(unix)]
+pub fn same_file(a: &Path, b: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let (a, b) = (fs::metadata(a)?, fs::metadata(b)?);
+    Ok(a.dev() == b.dev() && a.ino() == b.ino())
+}
+
+/// Without inode numbers, fall back to comparing contents.
+#[cfg(not(unix))]
+pub fn same_file(a: &Path, b: &Path) -> io::Result<bool> {
+    crate::file_compare::files_identical(a, b)
+}
+
+/// Number of hardlinks to the file behind `metadata`. Removing or replacing one of several
+/// links frees no space, since the others still hold the data.
+#[cfg(unix)]
+pub fn link_count(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink()
+}
+
+/// Without link counts, every file is assumed to have a single link.
+#[cfg(not(unix))]
+pub fn link_count(_metadata: &Metadata) -> u64 {
+    1
+}
+
+/// Lists the permission and ownership differences a duplicate would lose by becoming a
+/// link to the kept file, such as `mode 0644 -> 0600`. Empty if nothing changes.
+#[cfg(unix)]
+pub fn metadata_differences(duplicate: &Metadata, kept: &Metadata) -> Vec<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut differences = Vec::new();
+    let (from, to) = (duplicate.mode() & 0o7777, kept.mode() & 0o7777);
+    if from != to {
+        differences.push(format!("mode {from:04o} -> {to:04o}"));
+    }
+    if duplicate.uid() != kept.uid() {
+        differences.push(format!("owner {} -> {}", duplicate.uid(), kept.uid()));
+    }
+    if duplicate.gid() != kept.gid() {
+        differences.push(format!("group {} -> {}", duplicate.gid(), kept.gid()));
+    }
+    differences
+}
+
+/// Only the read-only flag is comparable outside Unix.
+#[cfg(not(unix))]
+pub fn metadata_differences(duplicate: &Metadata, kept: &Metadata) -> Vec<String> {
+    let (from, to) = (
+        duplicate.permissions().readonly(),
+        kept.permissions().readonly(),
+    );
+    if from == to {
+        Vec::new()
+    } else {
+        vec![format!("read-only {from} -> {to}")]
+    }
+}
@@ -1,55 +1,43 @@
-mod file_compare;
-mod filter;
+mod cli;
 mod handler;
-mod hashing;
-mod report;
-mod safe_delete;
-mod scanner;
 mod ui;
 
+use clap::{CommandFactory, FromArgMatches, error::ErrorKind};
+use cli::{Cli, Command};
 use handler::*;
-use ui::{print_banner, print_error, print_info};
-
-use std::{env, process};
+use ui::print_banner;
 
 /// Entry point of the program.
 /// Parses command-line arguments and dispatches to the appropriate handler function.
 fn main() {
+    // Exits with a usage error on unknown flags, missing arguments or bad values
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Err(message) = cli.command.validate() {
+        let mut command = Cli::command();
+        command.build();
+        let subcommand = matches.subcommand_name().unwrap_or_default();
+        match command.find_subcommand_mut(subcommand) {
+            Some(subcommand) => subcommand.error(ErrorKind::ValueValidation, message),
+            None => command.error(ErrorKind::ValueValidation, message),
+        }
+        .exit();
+    }
+
     // Display ASCII banner at launch
     print_banner();
 
-    // Collect command-line arguments
-    let args: Vec<String> = env::args().collect();
-
-    // Require at least one command
-    if args.len() < 2 {
-        print_error("Error: Not enough arguments.\n");
-        print_usage();
-        process::exit(1);
-    }
-
     // Match and dispatch commands
-    match args[1].as_str() {
-        "compare" => handle_compare_command(&args),
-        "scan" => handle_scan_command(&args),
-        "report" => handle_report_command(&args),
-        "delete" => handle_delete_command(&args),
-        "filter" => handle_filter_command(&args),
-        _ => {
-            print_error(&format!("Error: Unknown command '{}'", args[1]));
-            print_usage();
-            process::exit(1);
-        }
+    let sizes = cli.size_format();
+    match &cli.command {
+        Command::Compare(args) => handle_compare_command(args),
+        Command::Scan(args) => handle_scan_command(args, sizes),
+        Command::Report(args) => handle_report_command(args, sizes),
+        Command::Delete(args) => handle_delete_command(args, sizes),
+        Command::Link(args) => handle_link_command(args, sizes),
+        Command::Filter(args) => handle_filter_command(args, sizes),
+        Command::FindCopies(args) => handle_find_copies_command(args, sizes),
+        Command::Cache(args) => handle_cache_command(args, sizes),
+        Command::Restore(args) => handle_restore_command(args),
     }
 }
-
-/// Prints usage instructions for all commands.
-fn print_usage() {
-    print_info("📘 Usage Guide:\n");
-    println!("  hashlaser compare <file1> <file2>        🔍 Compare two files");
-    println!("  hashlaser scan <dir> [options]           🧪 Scan directory for duplicates");
-    println!("      Options: --min <bytes> --max <bytes> --ext txt,csv --regex <pattern>");
-    println!("  hashlaser report <dir> <output.json>     📄 Generate JSON report");
-    println!("  hashlaser delete <dir> [--dry-run]       🗑️ Delete duplicate files");
-    println!("  hashlaser filter <dir> [options]         🎯 Scan with filtering");
-}
@@ -0,0 +1,189 @@
+//! File ownership and permission bits: owner and group lookups, permission masks, and
+//! the check that a file can actually be deleted.
+//!
+//! These are only meaningful on Unix. Elsewhere, owners can only be given numerically,
+//! ownership and permission filters match nothing, and every file counts as deletable.
+
+use std::{io, path::Path, str::FromStr};
+
+/// A user given by name or numeric uid, e.g. `alice` or `1000`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UserId(pub u32);
+
+/// A group given by name or numeric gid, e.g. `staff` or `50`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GroupId(pub u32);
+
+impl FromStr for UserId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(uid) = s.parse() {
+            return Ok(UserId(uid));
+        }
+        lookup_user(s)
+            .map(UserId)
+            .ok_or_else(|| format!("unknown user '{s}'"))
+    }
+}
+
+impl FromStr for GroupId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(gid) = s.parse() {
+            return Ok(GroupId(gid));
+        }
+        lookup_group(s)
+            .map(GroupId)
+            .ok_or_else(|| format!("unknown group '{s}'"))
+    }
+}
+
+/// Permission bits to match, written in octal as for `find -perm`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermMask {
+    /// `644`: the permission bits are exactly these.
+    Exact(u32),
+    /// `-022`: all of these bits are set.
+    All(u32),
+    /// `/022`: at least one of these bits is set.
+    Any(u32),
+}
+
+impl PermMask {
+    /// Whether a file `mode` satisfies the mask; only the low twelve bits are compared.
+    pub fn matches(self, mode: u32) -> bool {
+        let mode = mode & 0o7777;
+        match self {
+            PermMask::Exact(bits) => mode == bits,
+            PermMask::All(bits) => mode & bits == bits,
+            PermMask::Any(bits) => bits == 0 || mode & bits != 0,
+        }
+    }
+}
+
+impl FromStr for PermMask {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, digits): (fn(u32) -> PermMask, &str) = match s.as_bytes().first() {
+            Some(b'-') => (PermMask::All, &s[1..]),
+            Some(b'/') => (PermMask::Any, &s[1..]),
+            _ => (PermMask::Exact, s),
+        };
+        match u32::from_str_radix(digits, 8) {
+            Ok(bits) if !digits.starts_with('+') && bits <= 0o7777 => Ok(kind(bits)),
+            _ => Err(format!(
+                "invalid permission mask '{s}' (expected octal bits such as 644, -022 or /111)"
+            )),
+        }
+    }
+}
+
+/// Checks that the current user can remove or replace `path`: its directory must be
+/// writable and searchable, not on a read-only filesystem, and if it is sticky (like
+/// `/tmp`) the user must own the file or the directory.
+#[cfg(unix)]
+pub fn check_deletable(path: &Path) -> io::Result<()> {
+    use std::{ffi::CString, fs, os::unix::ffi::OsStrExt, os::unix::fs::MetadataExt};
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let c_parent = CString::new(parent.as_os_str().as_bytes())?;
+    // SAFETY: `c_parent` is a valid NUL-terminated path
+    let rc = unsafe {
+        libc::faccessat(
+            libc::AT_FDCWD,
+            c_parent.as_ptr(),
+            libc::W_OK | libc::X_OK,
+            libc::AT_EACCESS,
+        )
+    };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let dir = fs::metadata(parent)?;
+    // The sticky bit, S_ISVTX
+    if dir.mode() & 0o1000 != 0 {
+        // SAFETY: geteuid cannot fail
+        let euid = unsafe { libc::geteuid() };
+        let file = fs::symlink_metadata(path)?;
+        if euid != 0 && euid != file.uid() && euid != dir.uid() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "sticky directory, and neither the file nor the directory is yours",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the current user can remove or replace `path`; always succeeds off Unix.
+#[cfg(not(unix))]
+pub fn check_deletable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Calls a `get*nam_r` style lookup, growing the buffer while it reports `ERANGE`.
+#[cfg(unix)]
+fn lookup_with_buffer(mut lookup: impl FnMut(&mut [libc::c_char]) -> libc::c_int) -> bool {
+    let mut buffer: Vec<libc::c_char> = vec![0; 1024];
+    loop {
+        match lookup(&mut buffer) {
+            libc::ERANGE if buffer.len() < 1 << 20 => buffer.resize(buffer.len() * 2, 0),
+            rc => return rc == 0,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn lookup_user(name: &str) -> Option<u32> {
+    let name = std::ffi::CString::new(name).ok()?;
+    // SAFETY: `passwd` is plain old data; getpwnam_r fills it in
+    let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result = std::ptr::null_mut();
+    let found = lookup_with_buffer(|buffer| unsafe {
+        // SAFETY: all pointers are valid for the duration of the call
+        libc::getpwnam_r(
+            name.as_ptr(),
+            &mut entry,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        )
+    });
+    (found && !result.is_null()).then_some(entry.pw_uid)
+}
+
+#[cfg(unix)]
+fn lookup_group(name: &str) -> Option<u32> {
+    let name = std::ffi::CString::new(name).ok()?;
+    // SAFETY: `group` is plain old data; getgrnam_r fills it in
+    let mut entry: libc::group = unsafe { std::mem::zeroed() };
+    let mut result = std::ptr::null_mut();
+    let found = lookup_with_buffer(|buffer| unsafe {
+        // SAFETY: all pointers are valid for the duration of the call
+        libc::getgrnam_r(
+            name.as_ptr(),
+            &mut entry,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        )
+    });
+    (found && !result.is_null()).then_some(entry.gr_gid)
+}
+
+#[cfg(not(unix))]
+fn lookup_user(_name: &str) -> Option<u32> {
+    None
+}
+
+#[cfg(not(unix))]
+fn lookup_group(_name: &str) -> Option<u32> {
+    None
+}
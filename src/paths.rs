@@ -0,0 +1,133 @@
+//! Lossless handling of paths that are not valid UTF-8.
+//!
+//! File names on Unix are arbitrary bytes, and legacy archives often hold Latin-1
+//! names that are not valid UTF-8. Such paths must survive a round trip through the
+//! hash cache, journals and reports unchanged, or a later run could act on the wrong
+//! file. The cache stores raw bytes via [`path_to_bytes`] and [`path_from_bytes`]; JSON
+//! files use the serde helpers below, which write UTF-8 paths as plain strings and any
+//! other path as `{"lossy": "caf�.txt", "hex": "636166e92e747874"}`, where `hex`
+//! holds the exact bytes and `lossy` is only a readable approximation.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+/// The raw bytes of `path`.
+///
+/// Exact on Unix; elsewhere paths are converted through lossy UTF-8.
+pub fn path_to_bytes(path: &Path) -> Cow<'_, [u8]> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Cow::Borrowed(path.as_os_str().as_bytes())
+    }
+    #[cfg(not(unix))]
+    {
+        match path.to_string_lossy() {
+            Cow::Borrowed(text) => Cow::Borrowed(text.as_bytes()),
+            Cow::Owned(text) => Cow::Owned(text.into_bytes()),
+        }
+    }
+}
+
+/// Rebuilds a path from bytes produced by [`path_to_bytes`].
+pub fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        PathBuf::from(std::ffi::OsString::from_vec(bytes))
+    }
+    #[cfg(not(unix))]
+    {
+        PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+/// How a single path is written to JSON.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum PathRepr {
+    Text(String),
+    Raw {
+        #[serde(default)]
+        lossy: String,
+        hex: String,
+    },
+}
+
+impl PathRepr {
+    fn new(path: &Path) -> Self {
+        match path.to_str() {
+            Some(text) => PathRepr::Text(text.to_string()),
+            None => PathRepr::Raw {
+                lossy: path.to_string_lossy().into_owned(),
+                hex: hex::encode(path_to_bytes(path)),
+            },
+        }
+    }
+
+    fn into_path<E: serde::de::Error>(self) -> Result<PathBuf, E> {
+        match self {
+            PathRepr::Text(text) => Ok(PathBuf::from(text)),
+            PathRepr::Raw { hex, .. } => hex::decode(&hex)
+                .map(path_from_bytes)
+                .map_err(|e| E::custom(format!("invalid hex path '{hex}': {e}"))),
+        }
+    }
+}
+
+/// Serde helpers for a `PathBuf` field: `#[serde(with = "crate::paths::serde_path")]`.
+pub mod serde_path {
+    use super::*;
+
+    pub fn serialize<P: AsRef<Path>, S: Serializer>(
+        path: &P,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        PathRepr::new(path.as_ref()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+        PathRepr::deserialize(deserializer)?.into_path()
+    }
+}
+
+/// Serde helpers for an `Option<PathBuf>` field.
+pub mod serde_path_option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        path: &Option<PathBuf>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        path.as_deref().map(PathRepr::new).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<PathBuf>, D::Error> {
+        Option::<PathRepr>::deserialize(deserializer)?
+            .map(PathRepr::into_path)
+            .transpose()
+    }
+}
+
+/// Serde helpers for a `Vec<PathBuf>` field.
+pub mod serde_path_vec {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(paths: &[PathBuf], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(paths.iter().map(|path| PathRepr::new(path)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<PathBuf>, D::Error> {
+        Vec::<PathRepr>::deserialize(deserializer)?
+            .into_iter()
+            .map(PathRepr::into_path)
+            .collect()
+    }
+}
@@ -0,0 +1,204 @@
+//! Copy-on-write clones ("reflinks") that let duplicates share extents on disk.
+//!
+//! Unlike hardlinks, reflinked files stay independent: each keeps its own inode,
+//! permissions and timestamps, and writing to one never changes the other. Only
+//! filesystems such as Btrfs and XFS support them; elsewhere [`reflink_file`] fails with
+//! [`io::ErrorKind::Unsupported`] and leaves the file untouched.
+
+use std::{fmt, io, path::Path};
+
+/// How a duplicate came to share extents with the kept file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReflinkMethod {
+    /// `FIDEDUPERANGE`: the kernel compared both files and shared their extents in place,
+    /// so the duplicate kept its inode and all of its metadata.
+    Deduped,
+    /// `FICLONE`: a clone of the kept file was renamed over the duplicate, with the
+    /// duplicate's permissions and timestamps copied over. Used when the kernel refuses
+    /// to deduplicate in place, e.g. for files opened without write access.
+    Cloned,
+}
+
+impl fmt::Display for ReflinkMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReflinkMethod::Deduped => f.write_str("deduped"),
+            ReflinkMethod::Cloned => f.write_str("cloned"),
+        }
+    }
+}
+
+/// Makes `path` share its data extents with `keep`, which must have identical contents.
+///
+/// Deduplication in place with `FIDEDUPERANGE` is tried first; the kernel locks both
+/// files and compares them itself, failing with [`io::ErrorKind::InvalidData`] if they
+/// differ. If that is not permitted, `path` is atomically replaced by a `FICLONE` of
+/// `keep` carrying the original permissions and timestamps.
+///
+/// # Returns
+///
+/// * `io::Result<ReflinkMethod>` - How the extents were shared. Fails with
+///   [`io::ErrorKind::Unsupported`] on filesystems without reflink support (tmpfs, ext4,
+///   ...) and with [`io::ErrorKind::CrossesDevices`] if the files are on different
+///   filesystems; `path` is unchanged in both cases.
+#[cfg(target_os = "linux")]
+pub fn reflink_file(path: &Path, keep: &Path) -> io::Result<ReflinkMethod> {
+    match linux::dedupe(path, keep) {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            linux::clone_over(path, keep).map(|_| ReflinkMethod::Cloned)
+        }
+        result => result.map(|_| ReflinkMethod::Deduped),
+    }
+}
+
+/// Reflinks are only implemented for Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn reflink_file(_path: &Path, _keep: &Path) -> io::Result<ReflinkMethod> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reflinks are not available on this platform",
+    ))
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use crate::link::replace_atomically;
+    use std::{
+        fs::{self, File, FileTimes, OpenOptions},
+        io,
+        os::fd::AsRawFd,
+        path::Path,
+    };
+
+    /// `_IOWR(0x94, 54, struct file_dedupe_range)` from `linux/fs.h`.
+    const FIDEDUPERANGE: u32 = 0xC018_9436;
+    /// `FILE_DEDUPE_RANGE_DIFFERS`: the ranges did not contain the same data.
+    const FILE_DEDUPE_RANGE_DIFFERS: i32 = 1;
+    /// Largest range submitted per call; some filesystems cap a single request at 16 MiB.
+    const MAX_DEDUPE_CHUNK: u64 = 16 * 1024 * 1024;
+
+    /// `struct file_dedupe_range` with room for a single destination.
+    #[repr(C)]
+    struct FileDedupeRange {
+        src_offset: u64,
+        src_length: u64,
+        dest_count: u16,
+        reserved1: u16,
+        reserved2: u32,
+        info: [FileDedupeRangeInfo; 1],
+    }
+
+    /// `struct file_dedupe_range_info`.
+    #[repr(C)]
+    struct FileDedupeRangeInfo {
+        dest_fd: i64,
+        dest_offset: u64,
+        bytes_deduped: u64,
+        status: i32,
+        reserved: u32,
+    }
+
+    /// Shares the extents of `keep` with `path` in place using `FIDEDUPERANGE`.
+    pub(super) fn dedupe(path: &Path, keep: &Path) -> io::Result<()> {
+        let source = File::open(keep)?;
+        // Owners may dedupe files they cannot write to (Linux 4.19+)
+        let dest = match OpenOptions::new().read(true).write(true).open(path) {
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => File::open(path)?,
+            result => result?,
+        };
+        let len = source.metadata()?.len();
+        if dest.metadata()?.len() != len {
+            return Err(differs());
+        }
+
+        let mut offset = 0;
+        while offset < len {
+            let mut range = FileDedupeRange {
+                src_offset: offset,
+                src_length: (len - offset).min(MAX_DEDUPE_CHUNK),
+                dest_count: 1,
+                reserved1: 0,
+                reserved2: 0,
+                info: [FileDedupeRangeInfo {
+                    dest_fd: dest.as_raw_fd() as i64,
+                    dest_offset: offset,
+                    bytes_deduped: 0,
+                    status: 0,
+                    reserved: 0,
+                }],
+            };
+            // SAFETY: `range` is a valid file_dedupe_range with exactly `dest_count`
+            // trailing info entries, and both descriptors stay open for the call.
+            let ret = unsafe {
+                libc::ioctl(
+                    source.as_raw_fd(),
+                    FIDEDUPERANGE as _,
+                    &mut range as *mut FileDedupeRange,
+                )
+            };
+            if ret < 0 {
+                return Err(classify(io::Error::last_os_error()));
+            }
+
+            let info = &range.info[0];
+            if info.status < 0 {
+                return Err(classify(io::Error::from_raw_os_error(-info.status)));
+            }
+            if info.status == FILE_DEDUPE_RANGE_DIFFERS {
+                return Err(differs());
+            }
+            if info.bytes_deduped == 0 {
+                return Err(io::Error::other("the kernel deduplicated no data"));
+            }
+            offset += info.bytes_deduped;
+        }
+        Ok(())
+    }
+
+    /// Atomically replaces `path` with a `FICLONE` of `keep`, keeping the permissions
+    /// and timestamps of `path`.
+    pub(super) fn clone_over(path: &Path, keep: &Path) -> io::Result<()> {
+        let source = File::open(keep)?;
+        let metadata = fs::metadata(path)?;
+        replace_atomically(path, |temp| {
+            let clone = OpenOptions::new().write(true).create_new(true).open(temp)?;
+            // SAFETY: FICLONE takes the source descriptor as its argument; both are open.
+            let ret = unsafe { libc::ioctl(clone.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) };
+            if ret < 0 {
+                return Err(classify(io::Error::last_os_error()));
+            }
+            let mut times = FileTimes::new();
+            if let Ok(modified) = metadata.modified() {
+                times = times.set_modified(modified);
+            }
+            if let Ok(accessed) = metadata.accessed() {
+                times = times.set_accessed(accessed);
+            }
+            clone.set_times(times)?;
+            fs::set_permissions(temp, metadata.permissions())
+        })
+    }
+
+    /// Turns the errno values filesystems use for "no reflink support" into
+    /// [`io::ErrorKind::Unsupported`] and `EXDEV` into [`io::ErrorKind::CrossesDevices`].
+    fn classify(error: io::Error) -> io::Error {
+        match error.raw_os_error() {
+            Some(libc::EOPNOTSUPP | libc::ENOTTY | libc::EINVAL | libc::ENOSYS) => io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the filesystem does not support reflinks",
+            ),
+            Some(libc::EXDEV) => io::Error::new(
+                io::ErrorKind::CrossesDevices,
+                "the files are on different filesystems",
+            ),
+            _ => error,
+        }
+    }
+
+    fn differs() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "contents differ from the kept file",
+        )
+    }
+}
@@ -1,19 +1,55 @@
+use crate::content_type;
+use crate::hashing::HashAlgorithm;
+use crate::size::SizeFormat;
 use serde::Serialize;
-use std::{collections::HashMap, fs::File, io::Write, path::Path};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 #[derive(Serialize)]
 struct DuplicateGroup {
     hash: String,
-    files: Vec<String>,
+    /// Size of each file in bytes, if it could still be read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    /// `size` in human units such as `1.5 MiB`; omitted when sizes are reported as bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_human: Option<String>,
+    /// MIME type detected from the content, such as `image/jpeg`; omitted if unrecognised.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mime: Option<&'static str>,
+    /// Paths that are not valid UTF-8 are written as `{"lossy", "hex"}` objects; see
+    /// [`crate::paths`].
+    #[serde(with = "crate::paths::serde_path_vec")]
+    files: Vec<PathBuf>,
 }
 
 #[derive(Serialize)]
 struct Report {
+    algorithm: HashAlgorithm,
     duplicates: Vec<DuplicateGroup>,
 }
 
+/// Writes the duplicate groups to `output_path` as pretty-printed JSON, recording
+/// the `algorithm` that produced the hashes. File names that are not valid UTF-8 are
+/// encoded losslessly.
 pub fn write_json_report<P: AsRef<Path>>(
-    hash_map: &HashMap<String, Vec<String>>,
+    hash_map: &HashMap<String, Vec<PathBuf>>,
+    algorithm: HashAlgorithm,
+    output_path: P,
+) -> std::io::Result<()> {
+    write_json_report_with(hash_map, algorithm, SizeFormat::default(), output_path)
+}
+
+/// Like [`write_json_report`], but with [`SizeFormat::Bytes`] each group only records its
+/// size as a byte count, without the `size_human` field.
+pub fn write_json_report_with<P: AsRef<Path>>(
+    hash_map: &HashMap<String, Vec<PathBuf>>,
+    algorithm: HashAlgorithm,
+    sizes: SizeFormat,
     output_path: P,
 ) -> std::io::Result<()> {
     // A vector of `DuplicateGroup` structs, where each group represents a set of files
@@ -37,13 +73,27 @@ pub fn write_json_report<P: AsRef<Path>>(
     let duplicates: Vec<DuplicateGroup> = hash_map
         .iter()
         .filter(|(_, files)| files.len() > 1)
-        .map(|(hash, files)| DuplicateGroup {
-            hash: hash.clone(),
-            files: files.clone(),
+        .map(|(hash, files)| {
+            let size = fs::metadata(&files[0]).ok().map(|metadata| metadata.len());
+            DuplicateGroup {
+                hash: hash.clone(),
+                size,
+                size_human: size
+                    .filter(|_| sizes == SizeFormat::Human)
+                    .map(|size| sizes.format(size)),
+                mime: content_type::detect(&files[0])
+                    .ok()
+                    .flatten()
+                    .map(|found| found.mime),
+                files: files.clone(),
+            }
         })
         .collect();
 
-    let report = Report { duplicates };
+    let report = Report {
+        algorithm,
+        duplicates,
+    };
     let json = serde_json::to_string_pretty(&report).expect("Serialization failed");
 
     let mut file = File::create(output_path)?;
@@ -1,4 +1,164 @@
-use std::{collections::HashMap, fs, io};
+use crate::error::DedupError;
+use crate::file_compare::files_identical;
+use crate::hashing::HashAlgorithm;
+use crate::journal::{JournalAction, JournalEntry, JournalWriter, absolute};
+use crate::keep::KeepPolicy;
+use crate::link::{
+    link_count, metadata_differences, replace_with_hardlink, replace_with_symlink, same_filesystem,
+    symlink_target,
+};
+use crate::ownership::check_deletable;
+use crate::reflink::{ReflinkMethod, reflink_file};
+use crate::trash::{quarantine_file, trash_file};
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// What happens to a duplicate that is not kept.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum DeleteMode {
+    /// Unlink the file permanently.
+    #[default]
+    Remove,
+    /// Move the file into the freedesktop.org trash.
+    Trash,
+    /// Move the file into this directory, mirroring its original path.
+    Quarantine(PathBuf),
+    /// Replace the file with a hardlink to the kept copy.
+    Hardlink,
+    /// Make the file a copy-on-write clone of the kept copy, sharing its extents.
+    Reflink,
+    /// Replace the file with a symbolic link to the kept copy, using a target relative
+    /// to the link's directory or an absolute one.
+    Symlink { relative: bool },
+}
+
+impl DeleteMode {
+    /// The verb used in progress and error messages, e.g. "Failed to link ...".
+    pub fn verb(&self) -> &'static str {
+        match self {
+            DeleteMode::Remove => "delete",
+            DeleteMode::Trash => "trash",
+            DeleteMode::Quarantine(_) => "quarantine",
+            DeleteMode::Hardlink => "link",
+            DeleteMode::Reflink => "clone",
+            DeleteMode::Symlink { .. } => "symlink",
+        }
+    }
+
+    /// Whether the mode removes or renames the duplicate's directory entry, which needs
+    /// permission to modify its directory. Reflinks share extents in place instead.
+    fn replaces_entry(&self) -> bool {
+        !matches!(self, DeleteMode::Reflink)
+    }
+}
+
+/// Options controlling how duplicates are deleted.
+#[derive(Debug, Clone)]
+pub struct DeleteOptions {
+    /// If true, only print which files would be deleted.
+    pub dry_run: bool,
+    /// Canonical paths that must never be deleted, such as the targets of symlinks
+    /// found during the scan. A protected file is preferred as the copy to keep.
+    pub protected: HashSet<PathBuf>,
+    /// If not empty, only files under these directories are deleted or replaced. Files
+    /// elsewhere stay in place and are preferred as the copy to keep, so a backup can be
+    /// cleared of what the original tree already holds.
+    pub only_under: Vec<PathBuf>,
+    /// Compare every candidate byte-for-byte with the kept file before deleting it.
+    /// Enabled by default; only disable it when explicitly requested.
+    pub verify: bool,
+    /// Rules deciding which file of each group is kept.
+    pub keep: KeepPolicy,
+    /// Whether duplicates are removed, trashed, quarantined or replaced by links.
+    pub mode: DeleteMode,
+    /// Where to write the undo journal. Ignored for dry runs and when nothing was touched.
+    pub journal: Option<PathBuf>,
+    /// The algorithm that produced the group hashes, recorded in the journal.
+    pub algorithm: HashAlgorithm,
+}
+
+/// What happened to one redundant file of a duplicate group.
+#[derive(Debug)]
+pub enum FileStatus {
+    /// The file was removed, moved or replaced as described, and journaled.
+    Done(JournalAction),
+    /// Dry run: the file passed every check and would have been handled.
+    WouldApply,
+    /// The file was left alone because it is protected, e.g. as a symlink target, or
+    /// because other hardlinks to it would keep its data allocated anyway.
+    Protected,
+    /// The file was deliberately left alone: verification failed, or the mode cannot
+    /// apply to it (different filesystem, no reflink support).
+    Skipped(DedupError),
+    /// Handling the file was attempted and failed.
+    Failed(DedupError),
+}
+
+impl FileStatus {
+    /// The error behind a skipped or failed file.
+    pub fn error(&self) -> Option<&DedupError> {
+        match self {
+            FileStatus::Skipped(error) | FileStatus::Failed(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// The outcome for one redundant file.
+#[derive(Debug)]
+pub struct FileOutcome {
+    /// The redundant file.
+    pub path: PathBuf,
+    /// The copy kept from its group.
+    pub kept: PathBuf,
+    pub status: FileStatus,
+}
+
+/// The per-file outcomes of a delete run.
+#[derive(Debug, Default)]
+pub struct DeleteReport {
+    /// One outcome per redundant file, group by group.
+    pub outcomes: Vec<FileOutcome>,
+    /// Where the journal was written, if one was written.
+    pub journal: Option<PathBuf>,
+    /// Why the run stopped before handling every group, if it did: the journal could
+    /// not be written, so nothing further was touched.
+    pub aborted: Option<DedupError>,
+}
+
+impl DeleteReport {
+    /// Outcomes of files that were skipped or failed.
+    pub fn problems(&self) -> impl Iterator<Item = &FileOutcome> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.status.error().is_some())
+    }
+
+    /// Whether every redundant file was handled (or, for a dry run, would be), apart from
+    /// protected ones.
+    pub fn is_complete(&self) -> bool {
+        self.aborted.is_none() && self.problems().next().is_none()
+    }
+}
+
+/// Default options: a real run with byte-for-byte verification and nothing protected.
+impl Default for DeleteOptions {
+    fn default() -> Self {
+        DeleteOptions {
+            dry_run: false,
+            protected: HashSet::new(),
+            only_under: Vec::new(),
+            verify: true,
+            keep: KeepPolicy::default(),
+            mode: DeleteMode::Remove,
+            journal: None,
+            algorithm: HashAlgorithm::default(),
+        }
+    }
+}
 
 /// Deletes duplicate files from the provided hash map, keeping only one copy per group.
 ///
@@ -9,46 +169,406 @@ use std::{collections::HashMap, fs, io};
 ///
 /// # Returns
 ///
-/// * `io::Result<()>` - Returns Ok(()) if all deletions succeed or if in dry run mode. Returns an error if any file deletion fails.
+/// * `Result<DeleteReport, DedupError>` - The outcome for every redundant file; see
+///   [`delete_duplicates_with`].
 ///
 /// # Example
 ///
 /// ```text
 /// // let mut duplicates = HashMap::new();
-/// // duplicates.insert("hash1".to_string(), vec!["file1.txt".to_string(), "file2.txt".to_string()]);
+/// // duplicates.insert("hash1".to_string(), vec![PathBuf::from("file1.txt"), PathBuf::from("file2.txt")]);
 /// // delete_duplicates(&duplicates, true)?;
 /// ```
 pub fn delete_duplicates(
-    duplicates: &HashMap<String, Vec<String>>,
+    duplicates: &HashMap<String, Vec<PathBuf>>,
     dry_run: bool,
-) -> io::Result<()> {
+) -> Result<DeleteReport, DedupError> {
+    delete_duplicates_with(
+        duplicates,
+        &DeleteOptions {
+            dry_run,
+            ..DeleteOptions::default()
+        },
+    )
+}
+
+/// Deletes duplicate files like [`delete_duplicates`], honouring the given [`DeleteOptions`].
+///
+/// The file to keep is chosen by `options.keep`. Files listed in `options.protected` are
+/// never deleted: if a group contains any, the keep policy chooses among those only, and
+/// any further protected files in the group are left in place. Files outside
+/// `options.only_under` are treated the same way, and groups without any file inside
+/// it are skipped.
+///
+/// Files the current user cannot delete (see [`check_deletable`]) are skipped before any
+/// other work, in dry runs too, so no action is planned that cannot be performed. Files
+/// with further hardlinks are left in place as well, since removing or replacing them
+/// would free nothing; only reflinking, which shares their data in place, touches them.
+/// Followed symbolic links are never touched in any mode, nor kept while the group holds
+/// a regular file.
+/// Without protected files, the keep policy prefers a file that stays anyway as the
+/// survivor.
+///
+/// Unless `options.verify` is off, each candidate is compared byte-for-byte with the
+/// kept file first. Candidates that differ, or cannot be read, are refused and reported
+/// instead of deleted, so a stale or colliding hash grouping can never lose data.
+///
+/// With [`DeleteMode::Trash`] or [`DeleteMode::Quarantine`], files are moved instead of
+/// unlinked. With [`DeleteMode::Hardlink`], each file is atomically replaced by a hardlink
+/// to the kept copy; a warning is printed when the file's permissions or owner differ
+/// from the kept copy's, since the link shares them. With [`DeleteMode::Reflink`], each
+/// file shares the kept copy's extents but stays an independent file; on filesystems
+/// without reflink support the file is left as it is and reported as skipped. In both
+/// modes, files on a different filesystem than the kept copy are skipped. With
+/// [`DeleteMode::Symlink`], each file is atomically replaced by a symbolic link to the
+/// kept copy, which may live in any directory or filesystem.
+///
+/// Every removal, move or replacement is recorded, along with the kept file and the
+/// original metadata, in a journal at `options.journal`, so the run can be undone with
+/// [`crate::journal::restore_journal`]. The journal is created before any file is touched,
+/// and each entry is written as soon as its action has run. If an entry cannot be written,
+/// the run stops there and [`DeleteReport::aborted`] says why.
+///
+/// # Arguments
+///
+/// * `duplicates` - Map from hash to the file paths sharing it.
+/// * `options` - Dry-run flag, protected paths, verification, keep policy and delete mode.
+///
+/// # Returns
+///
+/// * `Result<DeleteReport, DedupError>` - A [`FileOutcome`] for every redundant file, so
+///   callers can tell a partial run from a complete one with [`DeleteReport::is_complete`].
+///   Fails only if the journal cannot be created, before any file was touched.
+pub fn delete_duplicates_with(
+    duplicates: &HashMap<String, Vec<PathBuf>>,
+    options: &DeleteOptions,
+) -> Result<DeleteReport, DedupError> {
+    let journal_error = |path: &Path, source| DedupError::Journal {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut journal = match &options.journal {
+        Some(path) if !options.dry_run => Some(
+            JournalWriter::create(path, options.algorithm)
+                .map_err(|source| journal_error(path, source))?,
+        ),
+        _ => None,
+    };
+    let mut report = DeleteReport::default();
+
     // Iterate over each group of duplicate files
-    for (hash, files) in duplicates {
+    'groups: for (hash, files) in duplicates {
         // Skip groups with only one file (no duplicates)
         if files.len() <= 1 {
             continue;
         }
 
-        // Keep the first file, delete the rest
-        let (keep, delete) = files.split_first().unwrap();
+        let is_outside = |path: &PathBuf| {
+            !options.only_under.is_empty()
+                && !options.only_under.iter().any(|dir| path.starts_with(dir))
+        };
+        if files.iter().all(is_outside) {
+            continue;
+        }
+
+        // Keep a protected file if there is one, otherwise let the policy pick any file
+        let is_protected = |path: &PathBuf| {
+            !options.protected.is_empty()
+                && fs::canonicalize(path)
+                    .is_ok_and(|canonical| options.protected.contains(&canonical))
+        };
+        let protected: Vec<usize> = (0..files.len())
+            .filter(|&index| is_protected(&files[index]) || is_outside(&files[index]))
+            .collect();
+        // Files that cannot be removed stay anyway, so prefer keeping one of them
+        let mut undeletable: Vec<Option<io::Error>> = files
+            .iter()
+            .map(|path| {
+                options
+                    .mode
+                    .replaces_entry()
+                    .then(|| check_deletable(path).err())
+                    .flatten()
+            })
+            .collect();
+        // Removing one of several hardlinks frees nothing, so those are never touched
+        let shared: Vec<bool> = files
+            .iter()
+            .map(|path| {
+                options.mode.replaces_entry()
+                    && fs::symlink_metadata(path).is_ok_and(|metadata| link_count(&metadata) > 1)
+            })
+            .collect();
+        // Followed symlinks are left alone, and never kept: a link to a symlink would
+        // not lead to the data
+        let symlinks: Vec<bool> = files
+            .iter()
+            .map(|path| {
+                path.symlink_metadata()
+                    .is_ok_and(|m| m.file_type().is_symlink())
+            })
+            .collect();
+        let stuck: Vec<usize> = (0..files.len())
+            .filter(|&index| !symlinks[index] && (undeletable[index].is_some() || shared[index]))
+            .collect();
+        let files_only: Vec<usize> = (0..files.len()).filter(|&index| !symlinks[index]).collect();
+        let eligible: Vec<usize> = if !protected.is_empty() {
+            protected
+        } else if !stuck.is_empty() {
+            stuck
+        } else if !files_only.is_empty() {
+            files_only
+        } else {
+            (0..files.len()).collect()
+        };
+        let keep_index = options.keep.choose(files, &eligible).unwrap_or(0);
+        let keep = &files[keep_index];
 
         println!("\n🧬 Duplicate group (Hash: {})", hash);
-        println!("📂 Keeping: {}", keep);
+        println!("📂 Keeping: {}", keep.display());
 
         // Iterate over files to delete
-        for path in delete {
-            if dry_run {
-                // In dry run mode, just print what would be deleted
-                println!("🧪 Would delete: {}", path);
+        for (index, path) in files.iter().enumerate() {
+            if index == keep_index {
+                continue;
+            }
+            let status = if is_protected(path) {
+                println!("🛡️ Protected (symlink target): {}", path.display());
+                FileStatus::Protected
+            } else if is_outside(path) {
+                println!(
+                    "🛡️ Protected (outside the directories being cleaned up): {}",
+                    path.display()
+                );
+                FileStatus::Protected
+            } else if symlinks[index] {
+                println!("🛡️ Protected (symbolic link): {}", path.display());
+                FileStatus::Protected
+            } else if let Some(e) = undeletable[index].take() {
+                eprintln!(
+                    "⛔ Skipped {}: cannot {} it: {}",
+                    path.display(),
+                    options.mode.verb(),
+                    e
+                );
+                FileStatus::Skipped(DedupError::from_io(path, e))
+            } else if shared[index] {
+                println!(
+                    "🛡️ Protected (other hardlinks still hold its data): {}",
+                    path.display()
+                );
+                FileStatus::Protected
             } else {
-                // Attempt to delete the file and print the result
-                match fs::remove_file(path) {
-                    Ok(_) => println!("🗑️ Deleted: {}", path),
-                    Err(e) => eprintln!("❌ Failed to delete {}: {}", path, e),
+                let (status, entry) = handle_file(path, keep, hash, options);
+                if let (Some(entry), Some(writer)) = (entry, journal.as_mut())
+                    && let Err(source) = writer.append(&entry)
+                {
+                    let error = journal_error(writer.path(), source);
+                    eprintln!("❌ {}; stopping before any further file is touched", error);
+                    report.aborted = Some(error);
                 }
+                status
+            };
+            report.outcomes.push(FileOutcome {
+                path: path.clone(),
+                kept: keep.clone(),
+                status,
+            });
+            if report.aborted.is_some() {
+                break 'groups;
             }
         }
     }
 
-    Ok(())
+    if let Some(writer) = journal {
+        if writer.is_empty() {
+            // Nothing was touched, so there is nothing to undo
+            let _ = fs::remove_file(writer.path());
+        } else {
+            println!("\n📝 Journal written to {}", writer.path().display());
+            report.journal = Some(writer.path().to_path_buf());
+        }
+    }
+
+    Ok(report)
+}
+
+/// Verifies one redundant file against `keep` and removes, moves or replaces it,
+/// returning the journal entry describing what was done, if anything was.
+fn handle_file(
+    path: &Path,
+    keep: &Path,
+    hash: &str,
+    options: &DeleteOptions,
+) -> (FileStatus, Option<JournalEntry>) {
+    // Confirm the contents really match before touching anything
+    if options.verify {
+        match files_identical(keep, path) {
+            Ok(true) => {}
+            Ok(false) => {
+                eprintln!(
+                    "⛔ Refused {}: contents differ from {}",
+                    path.display(),
+                    keep.display()
+                );
+                return (
+                    FileStatus::Skipped(DedupError::ContentMismatch {
+                        path: path.into(),
+                        kept: keep.into(),
+                    }),
+                    None,
+                );
+            }
+            Err(e) => {
+                eprintln!(
+                    "⛔ Refused {}: could not verify contents: {}",
+                    path.display(),
+                    e
+                );
+                return (FileStatus::Skipped(DedupError::from_io(path, e)), None);
+            }
+        }
+    }
+
+    let verb = options.mode.verb();
+    let failed = |e: io::Error| {
+        eprintln!("❌ Failed to {} {}: {}", verb, path.display(), e);
+        FileStatus::Failed(DedupError::from_io(path, e))
+    };
+    let different_filesystem = || {
+        eprintln!(
+            "⛔ Skipped {}: not on the same filesystem as {}",
+            path.display(),
+            keep.display()
+        );
+        FileStatus::Skipped(DedupError::DifferentFilesystem {
+            path: path.into(),
+            kept: keep.into(),
+        })
+    };
+
+    if matches!(options.mode, DeleteMode::Hardlink | DeleteMode::Reflink) {
+        match same_filesystem(path, keep) {
+            Ok(true) => {}
+            Ok(false) => return (different_filesystem(), None),
+            Err(e) => return (failed(e), None),
+        }
+    }
+
+    if options.dry_run {
+        // In dry run mode, just print what would be deleted
+        match options.mode {
+            DeleteMode::Hardlink => {
+                println!("🧪 Would link: {} -> {}", path.display(), keep.display())
+            }
+            DeleteMode::Reflink => {
+                println!("🧪 Would clone: {} -> {}", path.display(), keep.display())
+            }
+            DeleteMode::Symlink { relative } => match symlink_target(path, keep, relative) {
+                Ok(target) => println!(
+                    "🧪 Would symlink: {} -> {}",
+                    path.display(),
+                    target.display()
+                ),
+                Err(e) => return (failed(e), None),
+            },
+            _ => println!("🧪 Would delete: {}", path.display()),
+        }
+        return (FileStatus::WouldApply, None);
+    }
+
+    // Capture the metadata the journal needs before the file goes away
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) => return (failed(e), None),
+    };
+
+    // Remove, move or replace the file and print the result
+    let result = match &options.mode {
+        DeleteMode::Remove => fs::remove_file(path).map(|_| (JournalAction::Removed, None)),
+        DeleteMode::Trash => trash_file(path).map(|dest| (JournalAction::Trashed, Some(dest))),
+        DeleteMode::Quarantine(dir) => {
+            quarantine_file(path, dir).map(|dest| (JournalAction::Quarantined, Some(dest)))
+        }
+        DeleteMode::Hardlink => {
+            warn_metadata_change(path, &metadata, keep);
+            replace_with_hardlink(path, keep).map(|_| (JournalAction::Hardlinked, None))
+        }
+        DeleteMode::Symlink { relative } => {
+            warn_metadata_change(path, &metadata, keep);
+            replace_with_symlink(path, keep, *relative).map(|target| {
+                println!("↪️ Symlinked: {} -> {}", path.display(), target.display());
+                (JournalAction::Symlinked, None)
+            })
+        }
+        DeleteMode::Reflink => reflink_file(path, keep).map(|method| {
+            println!(
+                "🪞 Cloned ({}): {} -> {}",
+                method,
+                path.display(),
+                keep.display()
+            );
+            // A clone renamed into place is a new inode owned by us
+            if method == ReflinkMethod::Cloned
+                && let Ok(clone_metadata) = fs::metadata(path)
+            {
+                let differences = metadata_differences(&metadata, &clone_metadata);
+                if !differences.is_empty() {
+                    eprintln!(
+                        "⚠️ {} could not keep its metadata: {}",
+                        path.display(),
+                        differences.join(", ")
+                    );
+                }
+            }
+            (JournalAction::Reflinked, None)
+        }),
+    };
+
+    let status = match result {
+        Ok((action, dest)) => {
+            let mut entry = JournalEntry::new(action, path, keep, hash, &metadata);
+            match dest {
+                Some(dest) => {
+                    println!("📦 Moved: {} -> {}", path.display(), dest.display());
+                    entry.moved_to = Some(absolute(&dest));
+                }
+                None if action == JournalAction::Hardlinked => {
+                    println!("🔗 Linked: {} -> {}", path.display(), keep.display())
+                }
+                None if matches!(action, JournalAction::Reflinked | JournalAction::Symlinked) => {}
+                None => println!("🗑️ Deleted: {}", path.display()),
+            }
+            return (FileStatus::Done(action), Some(entry));
+        }
+        Err(e) if options.mode == DeleteMode::Reflink => match e.kind() {
+            io::ErrorKind::CrossesDevices => different_filesystem(),
+            io::ErrorKind::Unsupported => {
+                eprintln!(
+                    "⏭️ Skipped {}: {}; left as a separate copy",
+                    path.display(),
+                    e
+                );
+                FileStatus::Skipped(DedupError::from_io(path, e))
+            }
+            _ => failed(e),
+        },
+        Err(e) => failed(e),
+    };
+    (status, None)
+}
+
+/// Warns when a file about to become a link to `keep` has different permissions or owner,
+/// since the link will present the kept file's instead.
+fn warn_metadata_change(path: &Path, metadata: &fs::Metadata, keep: &Path) {
+    if let Ok(kept_metadata) = fs::metadata(keep) {
+        let differences = metadata_differences(metadata, &kept_metadata);
+        if !differences.is_empty() {
+            eprintln!(
+                "⚠️ {} takes the kept file's metadata: {}",
+                path.display(),
+                differences.join(", ")
+            );
+        }
+    }
 }
@@ -1,16 +1,150 @@
+use ignore::{Match, gitignore::Gitignore};
 use rayon::prelude::*;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs,
+    hash::Hash,
+    io,
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
 };
 
+use crate::cache::{FileStamp, HashCache};
+use crate::error::DedupError;
 use crate::filter::FilterOptions;
-use crate::hashing::hash_file;
+use crate::hashing::{HashAlgorithm, hash_file_sample, hash_file_with};
+use crate::link::link_count;
+
+/// Number of bytes hashed from each end of a file during the sample stage.
+pub const SAMPLE_SIZE: u64 = 4096;
+
+/// Ignore files honoured with [`ScanOptions::respect_ignore`], highest precedence first.
+pub const IGNORE_FILES: [&str; 3] = [".hashlaserignore", ".ignore", ".gitignore"];
+
+/// Options controlling how a scan hashes and groups files.
+#[derive(Debug, Default, Clone)]
+pub struct ScanOptions {
+    /// Hash algorithm used for the sample and full-hash stages.
+    pub algorithm: HashAlgorithm,
+    /// Persistent cache consulted before, and updated after, each full hash.
+    pub cache: Option<Arc<HashCache>>,
+    /// Whether symbolic links are followed during traversal.
+    pub symlinks: SymlinkPolicy,
+    /// Skip paths listed in the [`IGNORE_FILES`] found in each scanned directory.
+    pub respect_ignore: bool,
+    /// Only keep duplicate groups with files under at least two of the scanned roots.
+    pub cross_root: bool,
+    /// Only keep duplicate groups with at least one file under each of these directories,
+    /// compared by path prefix, e.g. to find what a backup shares with the original.
+    pub present_in: Vec<PathBuf>,
+}
+
+/// How directory traversal treats symbolic links.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Resolve links and scan what they point to, skipping directories already visited.
+    Follow,
+    /// Leave links alone and report them separately in [`ScanResult::symlinks`].
+    #[default]
+    NoFollow,
+}
+
+/// A symbolic link that was found but not followed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymlinkEntry {
+    /// Path of the link itself.
+    pub link: PathBuf,
+    /// Canonical path of the file it resolves to, or `None` if the link is dangling.
+    pub target: Option<PathBuf>,
+}
+
+/// Per-stage counts collected while scanning, showing how much each stage pruned.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ScanStats {
+    /// Files that passed the filters and entered the pipeline.
+    pub files_scanned: usize,
+    /// Files sharing their exact byte length with at least one other file.
+    pub size_candidates: usize,
+    /// Files still colliding after hashing a head/tail sample.
+    pub sample_candidates: usize,
+    /// Files confirmed as duplicates by a full content hash.
+    pub duplicate_files: usize,
+    /// Number of confirmed duplicate groups.
+    pub duplicate_groups: usize,
+    /// Full hashes served from the persistent cache instead of being recomputed.
+    pub cache_hits: usize,
+    /// Paths skipped because they are hardlinks to a file already in the scan.
+    pub hardlinked_files: usize,
+    /// Bytes that removing all but one file of every duplicate group would free. Files
+    /// with further hardlinks and followed symlinks free nothing when removed, so they
+    /// are not counted.
+    pub reclaimable_bytes: u64,
+}
+
+/// The outcome of a directory scan: the duplicate groups, pipeline statistics and the
+/// paths that could not be scanned.
+#[derive(Debug, Default)]
+pub struct ScanResult {
+    /// Map from full content hash to the files sharing it. Only groups with two or more files are kept.
+    pub duplicates: HashMap<String, Vec<PathBuf>>,
+    /// Per-stage counts for this scan.
+    pub stats: ScanStats,
+    /// The algorithm that produced the hashes in `duplicates`.
+    pub algorithm: HashAlgorithm,
+    /// Paths that are hardlinks to the same inode, already sharing their storage.
+    ///
+    /// Each group is sorted; its first path is the one that represents the inode in
+    /// `duplicates`, the others are left out of duplicate detection entirely.
+    pub hardlinks: Vec<Vec<PathBuf>>,
+    /// Symbolic links left untraversed under [`SymlinkPolicy::NoFollow`], sorted by link path.
+    pub symlinks: Vec<SymlinkEntry>,
+    /// Paths that were skipped because listing, reading or hashing them failed, such as
+    /// unreadable directories or files deleted while the scan was running. Sorted by path.
+    pub errors: Vec<DedupError>,
+    /// The directories that were scanned, in the order given.
+    pub roots: Vec<PathBuf>,
+}
+
+impl ScanResult {
+    /// Index into [`ScanResult::roots`] of the root `path` lies under. For nested roots the
+    /// innermost one wins.
+    pub fn root_of(&self, path: &Path) -> Option<usize> {
+        root_of(&self.roots, path)
+    }
+
+    /// Indices of the roots the files of a group lie under, in root order.
+    pub fn roots_of(&self, files: &[PathBuf]) -> Vec<usize> {
+        roots_of(&self.roots, files)
+    }
+}
+
+fn root_of(roots: &[PathBuf], path: &Path) -> Option<usize> {
+    roots
+        .iter()
+        .enumerate()
+        .filter(|(_, root)| path.starts_with(root))
+        .max_by_key(|(_, root)| root.components().count())
+        .map(|(index, _)| index)
+}
+
+fn roots_of(roots: &[PathBuf], files: &[PathBuf]) -> Vec<usize> {
+    let mut indices: Vec<usize> = files
+        .iter()
+        .filter_map(|path| root_of(roots, path))
+        .collect();
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+}
 
 /// Scans a directory recursively and finds duplicate files based on SHA-256 hash.
 ///
+/// This is a convenience wrapper around [`scan_directory`] using the default
+/// [`ScanOptions`] and discarding the stage statistics.
+///
 /// # Arguments
 ///
 /// * `dir` - The root directory to scan for files.
@@ -19,73 +153,550 @@ use crate::hashing::hash_file;
 /// # Returns
 ///
 /// A `HashMap` where the key is the SHA-256 hash of the file contents,
-/// and the value is a vector of file paths (as strings) that have that hash.
+/// and the value is a vector of file paths that have that hash.
+/// Files without a duplicate are not included.
 ///
 /// # Example
 ///
 /// ```text
 /// // let duplicates = scan_directory_for_duplicates("/some/path", &filter_options);
 /// ```
-pub fn scan_directory_for_duplicates(
-    dir: &str,
+pub fn scan_directory_for_duplicates<P: AsRef<Path>>(
+    dir: P,
     filters: &FilterOptions,
-) -> HashMap<String, Vec<String>> {
-    // Collect all files recursively from the directory
-    let files = collect_files_recursively(Path::new(dir));
+) -> HashMap<String, Vec<PathBuf>> {
+    scan_directory(dir, filters, &ScanOptions::default()).duplicates
+}
 
-    // Filter files based on the provided filter options
-    let filtered_files: Vec<PathBuf> = files
-        .into_iter()
-        .filter(|path| filters.matches(path))
+/// Scans a directory recursively using a multi-stage pipeline and reports per-stage counts.
+///
+/// Hardlinks to the same inode are collapsed into one logical file before any hashing,
+/// so they are never reported as duplicates of each other. Followed symlinks are kept
+/// out of that merge: a link to a file already in the scan is dropped, and a link to a
+/// file elsewhere stands for that file under its own path. Files are then narrowed down
+/// in three stages, each only looking at survivors of the previous one:
+///
+/// 1. **Size** – files are grouped by exact byte length; unique sizes cannot have a duplicate.
+/// 2. **Sample** – same-size files are grouped by a hash of their first and last [`SAMPLE_SIZE`] bytes.
+/// 3. **Full hash** – remaining candidates are hashed in full, reusing hashes from
+///    [`ScanOptions::cache`] for files whose size, timestamps and inode are unchanged.
+///
+/// # Arguments
+///
+/// * `dir` - The root directory to scan for files.
+/// * `filters` - Filter options to apply to each file.
+/// * `options` - Scan options such as the hash algorithm.
+///
+/// # Returns
+///
+/// A [`ScanResult`] with the duplicate groups and the [`ScanStats`] for each stage.
+/// Paths that fail at any stage are left out of the groups and listed in
+/// [`ScanResult::errors`] instead.
+pub fn scan_directory<P: AsRef<Path>>(
+    dir: P,
+    filters: &FilterOptions,
+    options: &ScanOptions,
+) -> ScanResult {
+    scan_roots(&[dir], filters, options)
+}
+
+/// Scans several directories in one pass, like [`scan_directory`], so duplicates are
+/// found across them as well as within each.
+///
+/// Each file's path-based filters see its path relative to its own root. A directory
+/// reachable from several roots is only walked once. With [`ScanOptions::cross_root`]
+/// or [`ScanOptions::present_in`], duplicate groups that do not span the requested
+/// roots are dropped before the statistics are computed.
+///
+/// # Arguments
+///
+/// * `roots` - The directories to scan.
+/// * `filters` - Filter options to apply to each file.
+/// * `options` - Scan options such as the hash algorithm.
+///
+/// # Returns
+///
+/// A [`ScanResult`] whose [`ScanResult::roots`] tell which root each file was found under.
+pub fn scan_roots<P: AsRef<Path>>(
+    roots: &[P],
+    filters: &FilterOptions,
+    options: &ScanOptions,
+) -> ScanResult {
+    let algorithm = options.algorithm;
+    let roots: Vec<PathBuf> = roots
+        .iter()
+        .map(|root| root.as_ref().to_path_buf())
         .collect();
 
-    // Shared, thread-safe map to store hashes and their corresponding file paths
-    let hash_map: Arc<Mutex<HashMap<String, Vec<String>>>> = Arc::new(Mutex::new(HashMap::new()));
+    // Collect all files recursively from the directories. Sorting makes the
+    // path chosen to represent a hardlinked inode deterministic.
+    let Walked {
+        files: mut filtered_files,
+        symlinks,
+        mut errors,
+    } = walk_roots(&roots, filters, options);
+    filtered_files.sort();
 
-    // Compute hashes in parallel and populate the map
-    filtered_files.par_iter().for_each(|file_path| {
-        if let Ok(hash) = hash_file(file_path.to_str().unwrap()) {
-            let mut map = hash_map.lock().unwrap();
-            map.entry(hash)
-                .or_default()
-                .push(file_path.to_string_lossy().to_string());
+    let mut stats = ScanStats {
+        files_scanned: filtered_files.len(),
+        ..ScanStats::default()
+    };
+
+    // Stage 1: collapse hardlinks, then group by exact byte length
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    let mut inodes: HashMap<(u64, u64), Vec<PathBuf>> = HashMap::new();
+    let mut followed = Vec::new();
+    for path in filtered_files {
+        let metadata = match fs::symlink_metadata(&path).and_then(|metadata| {
+            if metadata.file_type().is_symlink() {
+                fs::metadata(&path).map(|target| (target, true))
+            } else {
+                Ok((metadata, false))
+            }
+        }) {
+            Ok((metadata, false)) => metadata,
+            // Followed symlinks never join the inode merge, so they cannot stand for a file
+            Ok((target, true)) => {
+                followed.push((file_id(&target), target.len(), path));
+                continue;
+            }
+            Err(e) => {
+                errors.push(DedupError::from_io(&path, e));
+                continue;
+            }
+        };
+        if let Some(id) = file_id(&metadata) {
+            let links = inodes.entry(id).or_default();
+            links.push(path.clone());
+            if links.len() > 1 {
+                continue;
+            }
+        }
+        by_size.entry(metadata.len()).or_default().push(path);
+    }
+    // A symlink to a file already in the scan adds nothing; the first one to a file
+    // elsewhere stands in for it
+    let mut outside = HashSet::new();
+    for (id, size, path) in followed {
+        if let Some(id) = id
+            && (inodes.contains_key(&id) || !outside.insert(id))
+        {
+            continue;
         }
-    });
+        by_size.entry(size).or_default().push(path);
+    }
+    let mut hardlinks: Vec<Vec<PathBuf>> = inodes
+        .into_values()
+        .filter(|links| links.len() > 1)
+        .collect();
+    hardlinks.sort();
+    stats.hardlinked_files = hardlinks.iter().map(|links| links.len() - 1).sum();
+    let size_groups: Vec<(u64, Vec<PathBuf>)> = by_size
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .collect();
+    stats.size_candidates = size_groups.iter().map(|(_, files)| files.len()).sum();
 
-    // Unwrap the Arc and Mutex to return the final HashMap
-    Arc::try_unwrap(hash_map).unwrap().into_inner().unwrap()
+    // Stage 2: hash a head/tail sample of large files. Small files are read in
+    // full by the next stage anyway, so sampling them would only add I/O.
+    let (small, large): (Vec<_>, Vec<_>) = size_groups
+        .into_iter()
+        .partition(|(size, _)| *size <= SAMPLE_SIZE * 2);
+    let mut sample_groups: Vec<Vec<PathBuf>> = small.into_iter().map(|(_, files)| files).collect();
+    sample_groups.extend(
+        refine_groups(
+            large.into_iter().map(|(_, files)| files).collect(),
+            |path| hash_file_sample(path, SAMPLE_SIZE, algorithm),
+            &mut errors,
+        )
+        .into_iter()
+        .map(|(_, files)| files),
+    );
+    stats.sample_candidates = sample_groups.iter().map(Vec::len).sum();
+
+    // Stage 3: confirm with a full content hash
+    let cache_hits = AtomicUsize::new(0);
+    let duplicates: HashMap<String, Vec<PathBuf>> = refine_groups(
+        sample_groups,
+        |path| full_hash(path, algorithm, options.cache.as_deref(), &cache_hits),
+        &mut errors,
+    )
+    .into_iter()
+    .filter(|(_, files)| {
+        (!options.cross_root || roots_of(&roots, files).len() > 1)
+            && options
+                .present_in
+                .iter()
+                .all(|dir| files.iter().any(|path| path.starts_with(dir)))
+    })
+    .collect();
+    stats.cache_hits = cache_hits.into_inner();
+    stats.duplicate_groups = duplicates.len();
+    stats.duplicate_files = duplicates.values().map(Vec::len).sum();
+    stats.reclaimable_bytes = duplicates
+        .values()
+        .filter_map(|files| {
+            let size = fs::metadata(&files[0]).ok()?.len();
+            // Removing a link frees nothing. A file with further hardlinks is kept in
+            // preference, freeing every other copy; otherwise one copy must stay.
+            let regular: Vec<u64> = files
+                .iter()
+                .filter_map(|path| fs::symlink_metadata(path).ok())
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| link_count(&metadata))
+                .collect();
+            let single = regular.iter().filter(|&&links| links == 1).count();
+            let freed = if single < regular.len() {
+                single
+            } else {
+                single.saturating_sub(1)
+            };
+            Some(size * freed as u64)
+        })
+        .sum();
+    errors.sort_by(|a, b| a.path().cmp(b.path()));
+
+    ScanResult {
+        duplicates,
+        stats,
+        algorithm,
+        hardlinks,
+        symlinks,
+        errors,
+        roots,
+    }
 }
 
-/// Recursively collects all file paths under the given directory.
+/// Everything a traversal of the scan roots found.
+pub(crate) struct Walked {
+    /// Files that passed `filters`, in traversal order.
+    pub files: Vec<PathBuf>,
+    /// Symbolic links left untraversed, sorted by link path.
+    pub symlinks: Vec<SymlinkEntry>,
+    pub errors: Vec<DedupError>,
+}
+
+/// Walks each of `roots` in turn, keeping the files that pass `filters` as seen from
+/// their own root. A directory reachable from several roots is only walked once.
+pub(crate) fn walk_roots(
+    roots: &[PathBuf],
+    filters: &FilterOptions,
+    options: &ScanOptions,
+) -> Walked {
+    let mut walk = Walk {
+        root: PathBuf::new(),
+        policy: options.symlinks,
+        filters,
+        respect_ignore: options.respect_ignore,
+        ignores: Vec::new(),
+        visited: HashSet::new(),
+        files: Vec::new(),
+        symlinks: Vec::new(),
+        errors: Vec::new(),
+    };
+    let filter = filters.to_expr();
+    let mut files = Vec::new();
+    for root in roots {
+        walk.root.clone_from(root);
+        collect_files_recursively(root, &mut walk);
+        files.extend(walk.files.drain(..).filter(|path| {
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            filter
+                .as_ref()
+                .is_none_or(|expr| expr.matches(path, relative))
+        }));
+    }
+    walk.symlinks.sort_by(|a, b| a.link.cmp(&b.link));
+    Walked {
+        files,
+        symlinks: walk.symlinks,
+        errors: walk.errors,
+    }
+}
+
+/// Returns the `(device, inode)` pair identifying the file behind `metadata`.
+///
+/// Always `None` on platforms without inode numbers, where hardlinks are not collapsed.
+pub(crate) fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Some((metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Re-hashes every file of existing duplicate groups with another algorithm.
+///
+/// Groups found with a fast, non-cryptographic hash should be confirmed this way
+/// before acting on them. Files that no longer match the rest of their group are
+/// split off, and groups left with a single file are dropped.
 ///
 /// # Arguments
 ///
-/// * `dir` - The directory path to start scanning from.
+/// * `duplicates` - Duplicate groups as returned by [`scan_directory`].
+/// * `algorithm` - The algorithm to confirm the groups with.
 ///
 /// # Returns
 ///
-/// A vector of `PathBuf` objects representing all files found under the directory.
+/// The confirmed groups, keyed by their hash under `algorithm`, and the files that
+/// could not be re-hashed.
+pub fn rehash_groups(
+    duplicates: &HashMap<String, Vec<PathBuf>>,
+    algorithm: HashAlgorithm,
+) -> (HashMap<String, Vec<PathBuf>>, Vec<DedupError>) {
+    let groups = duplicates.values().cloned().collect();
+    let mut errors = Vec::new();
+    let confirmed = refine_groups(groups, |path| hash_file_with(path, algorithm), &mut errors)
+        .into_iter()
+        .collect();
+    (confirmed, errors)
+}
+
+/// Hashes a file in full, going through the cache when one is configured.
+pub(crate) fn full_hash(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    cache: Option<&HashCache>,
+    cache_hits: &AtomicUsize,
+) -> io::Result<String> {
+    let Some(cache) = cache else {
+        return hash_file_with(path, algorithm);
+    };
+
+    // Key by absolute path so scans started from different directories share entries
+    let key = std::path::absolute(path)?;
+    let stamp = FileStamp::from_metadata(&fs::metadata(path)?);
+    if let Some(hash) = cache.get(&key, algorithm, &stamp) {
+        cache_hits.fetch_add(1, Ordering::Relaxed);
+        return Ok(hash);
+    }
+
+    let hash = hash_file_with(path, algorithm)?;
+    cache.insert(&key, algorithm, stamp, hash.clone());
+    Ok(hash)
+}
+
+/// Splits each candidate group further by a per-file key computed in parallel.
+///
+/// Files for which `key` fails are dropped and their errors appended to `errors`. Only
+/// sub-groups with two or more files are returned, with their paths sorted so results
+/// do not depend on thread scheduling.
+fn refine_groups<K, F>(
+    groups: Vec<Vec<PathBuf>>,
+    key: F,
+    errors: &mut Vec<DedupError>,
+) -> Vec<(K, Vec<PathBuf>)>
+where
+    K: Hash + Eq + Send,
+    F: Fn(&Path) -> io::Result<K> + Sync,
+{
+    // Compute keys in parallel, remembering which group each file came from
+    let keyed: Vec<(usize, io::Result<K>, PathBuf)> = groups
+        .into_iter()
+        .enumerate()
+        .flat_map(|(index, files)| files.into_iter().map(move |path| (index, path)))
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|(index, path)| (index, key(&path), path))
+        .collect();
+
+    let mut refined: HashMap<(usize, K), Vec<PathBuf>> = HashMap::new();
+    for (index, k, path) in keyed {
+        match k {
+            Ok(k) => refined.entry((index, k)).or_default().push(path),
+            Err(e) => errors.push(DedupError::from_io(&path, e)),
+        }
+    }
+
+    refined
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .map(|((_, k), mut files)| {
+            files.sort();
+            (k, files)
+        })
+        .collect()
+}
+
+/// Identifies a directory already visited while following symlinks.
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum DirId {
+    /// `(device, inode)` pair, used wherever the platform provides one.
+    Inode(u64, u64),
+    /// Canonical path, the fallback on platforms without inode numbers.
+    Path(PathBuf),
+}
+
+impl DirId {
+    fn of(path: &Path, metadata: &fs::Metadata) -> Option<Self> {
+        match file_id(metadata) {
+            Some((dev, ino)) => Some(DirId::Inode(dev, ino)),
+            None => fs::canonicalize(path).ok().map(DirId::Path),
+        }
+    }
+}
+
+/// State threaded through a directory traversal.
+struct Walk<'a> {
+    /// The scan root being walked; exclusions see paths relative to it.
+    root: PathBuf,
+    policy: SymlinkPolicy,
+    /// Used to prune excluded directories before they are listed.
+    filters: &'a FilterOptions,
+    respect_ignore: bool,
+    /// Ignore files of the directories currently being walked, outermost first; each
+    /// level is ordered like [`IGNORE_FILES`].
+    ignores: Vec<Vec<Gitignore>>,
+    visited: HashSet<DirId>,
+    files: Vec<PathBuf>,
+    symlinks: Vec<SymlinkEntry>,
+    errors: Vec<DedupError>,
+}
+
+impl Walk<'_> {
+    /// Whether the directory at `path` is pruned by the exclusion filters.
+    fn excludes_dir(&self, path: &Path) -> bool {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        self.filters.excludes_dir(relative)
+    }
+}
+
+/// Recursively collects all file paths under the given directory.
+///
+/// Symlinks are handled according to `walk.policy`: with [`SymlinkPolicy::NoFollow`]
+/// they are recorded in `walk.symlinks` and not traversed; with [`SymlinkPolicy::Follow`]
+/// they are resolved, and directories already visited (by device and inode) are skipped
+/// so symlink loops terminate. Directories and entries that cannot be read are
+/// recorded in `walk.errors`. Directories excluded by `walk.filters` are pruned without
+/// being listed. With `walk.respect_ignore`, the [`IGNORE_FILES`] of every directory
+/// apply to it and everything below it, like git and ripgrep: the closest match wins,
+/// `!pattern` re-includes a path, and ignored directories are pruned as well.
+///
+/// # Arguments
+///
+/// * `dir` - The directory path to start scanning from.
+/// * `walk` - Traversal state receiving the files and symlinks found.
 ///
 /// # Example
 ///
 /// ```text
-/// // let files = collect_files_recursively(Path::new("/some/path"));
+/// // collect_files_recursively(Path::new("/some/path"), &mut walk);
 /// ```
-fn collect_files_recursively(dir: &Path) -> Vec<PathBuf> {
-    // Vector to store all found file paths
-    let mut all_files = Vec::new();
-    // Read directory entries, if possible
-    if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_dir() {
-                // Recurse into subdirectories
-                all_files.extend(collect_files_recursively(&path));
-            } else if path.is_file() {
-                // Add file to the list
-                all_files.push(path);
+fn collect_files_recursively(dir: &Path, walk: &mut Walk<'_>) {
+    // Remember this directory so a symlink leading back to it is not followed again
+    if let Some(id) = fs::metadata(dir)
+        .ok()
+        .and_then(|metadata| DirId::of(dir, &metadata))
+        && !walk.visited.insert(id)
+    {
+        return;
+    }
+
+    // Read directory entries, recording directories that cannot be listed
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            walk.errors.push(DedupError::from_io(dir, e));
+            return;
+        }
+    };
+    let has_ignores = walk.respect_ignore && push_ignore_files(dir, walk);
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                walk.errors.push(DedupError::from_io(dir, e));
+                continue;
+            }
+        };
+        let path = entry.path();
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(e) => {
+                walk.errors.push(DedupError::from_io(&path, e));
+                continue;
             }
+        };
+        if is_ignored(walk, &path, file_type.is_dir()) {
+            continue;
+        }
+
+        if file_type.is_symlink() {
+            match walk.policy {
+                SymlinkPolicy::NoFollow => walk.symlinks.push(SymlinkEntry {
+                    target: fs::canonicalize(&path).ok(),
+                    link: path.clone(),
+                }),
+                SymlinkPolicy::Follow => match fs::metadata(&path) {
+                    Ok(metadata) if metadata.is_dir() => {
+                        if !walk.excludes_dir(&path) {
+                            collect_files_recursively(&path, walk);
+                        }
+                    }
+                    Ok(metadata) if metadata.is_file() => walk.files.push(path),
+                    // Dangling links and special files are skipped
+                    Ok(_) => {}
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => walk.errors.push(DedupError::from_io(&path, e)),
+                },
+            }
+        } else if file_type.is_dir() {
+            // Recurse into subdirectories that are not excluded
+            if !walk.excludes_dir(&path) {
+                collect_files_recursively(&path, walk);
+            }
+        } else if file_type.is_file() {
+            // Add file to the list
+            walk.files.push(path);
+        }
+    }
+
+    if has_ignores {
+        walk.ignores.pop();
+    }
+}
+
+/// Loads the [`IGNORE_FILES`] present in `dir` onto `walk.ignores`, recording lines
+/// that cannot be parsed. Returns whether a level was pushed.
+fn push_ignore_files(dir: &Path, walk: &mut Walk<'_>) -> bool {
+    let mut level = Vec::new();
+    for name in IGNORE_FILES {
+        let path = dir.join(name);
+        if !path.is_file() {
+            continue;
+        }
+        let (gitignore, error) = Gitignore::new(&path);
+        if let Some(error) = error {
+            walk.errors.push(DedupError::Io {
+                path,
+                source: io::Error::new(io::ErrorKind::InvalidData, error),
+            });
+        }
+        if !gitignore.is_empty() {
+            level.push(gitignore);
+        }
+    }
+    if level.is_empty() {
+        return false;
+    }
+    walk.ignores.push(level);
+    true
+}
+
+/// Whether the closest ignore rule matching `path` excludes it.
+fn is_ignored(walk: &Walk<'_>, path: &Path, is_dir: bool) -> bool {
+    for gitignore in walk.ignores.iter().rev().flatten() {
+        match gitignore.matched(path, is_dir) {
+            Match::Ignore(_) => return true,
+            Match::Whitelist(_) => return false,
+            Match::None => {}
         }
     }
-    all_files
+    false
 }
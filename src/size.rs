@@ -0,0 +1,119 @@
+//! Byte sizes written for humans, such as `10MiB`, `2G` or `500k`.
+//!
+//! Units are case-insensitive. Decimal (SI) units are powers of 1000: `k`/`kB`, `M`/`MB`,
+//! `G`/`GB`, `T`/`TB` and `P`/`PB`. Binary (IEC) units are powers of 1024: `Ki`/`KiB`,
+//! `Mi`/`MiB`, `Gi`/`GiB`, `Ti`/`TiB` and `Pi`/`PiB`. A bare number or a `B` suffix means
+//! bytes, and fractions such as `1.5G` are rounded down to whole bytes. Sizes are printed
+//! with binary units, e.g. `1.5 MiB`.
+
+use std::{fmt, str::FromStr};
+
+/// Binary units used when printing, from smallest to largest.
+const IEC_UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+/// A number of bytes, parsed from and displayed in human units.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    /// The multiplier for a unit suffix, or `None` if the suffix is not a known unit.
+    fn multiplier(unit: &str) -> Option<u64> {
+        let multiplier = match unit.to_ascii_lowercase().as_str() {
+            "" | "b" => 1,
+            "k" | "kb" => 1_000,
+            "m" | "mb" => 1_000_000,
+            "g" | "gb" => 1_000_000_000,
+            "t" | "tb" => 1_000_000_000_000,
+            "p" | "pb" => 1_000_000_000_000_000,
+            "ki" | "kib" => 1 << 10,
+            "mi" | "mib" => 1 << 20,
+            "gi" | "gib" => 1 << 30,
+            "ti" | "tib" => 1 << 40,
+            "pi" | "pib" => 1 << 50,
+            _ => return None,
+        };
+        Some(multiplier)
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.len());
+        let (number, unit) = (&s[..split], s[split..].trim_start());
+        if number.is_empty() {
+            return Err(format!(
+                "invalid size '{s}' (expected e.g. 500k, 10MiB or 2G)"
+            ));
+        }
+
+        let Some(multiplier) = ByteSize::multiplier(unit) else {
+            return Err(format!(
+                "unknown size unit '{unit}' in '{s}' (expected B, k, M, G, T, P or KiB, MiB, GiB, TiB, PiB)"
+            ));
+        };
+        let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+        if number == "." || fraction.contains('.') {
+            return Err(format!(
+                "invalid size '{s}' (expected e.g. 500k, 10MiB or 2G)"
+            ));
+        }
+
+        let too_large = || format!("size '{s}' is too large");
+        let whole: u64 = if whole.is_empty() {
+            0
+        } else {
+            whole.parse().map_err(|_| too_large())?
+        };
+        let mut bytes = whole.checked_mul(multiplier).ok_or_else(too_large)?;
+        if !fraction.is_empty() {
+            let fraction: f64 = format!("0.{fraction}").parse().map_err(|_| too_large())?;
+            let extra = (fraction * multiplier as f64) as u64;
+            if multiplier == 1 && extra == 0 && fraction > 0.0 {
+                return Err(format!("size '{s}' is not a whole number of bytes"));
+            }
+            bytes = bytes.checked_add(extra).ok_or_else(too_large)?;
+        }
+        Ok(ByteSize(bytes))
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut value = self.0 as f64;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < IEC_UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            write!(f, "{} B", self.0)
+        } else {
+            write!(f, "{:.1} {}", value, IEC_UNITS[unit])
+        }
+    }
+}
+
+/// How sizes are rendered in command output and reports.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SizeFormat {
+    /// Binary units such as `1.5 MiB`.
+    #[default]
+    Human,
+    /// Exact byte counts, for scripts.
+    Bytes,
+}
+
+impl SizeFormat {
+    /// Renders `bytes` in this format, e.g. `1.5 MiB` or `1572864 bytes`.
+    pub fn format(self, bytes: u64) -> String {
+        match self {
+            SizeFormat::Human => ByteSize(bytes).to_string(),
+            SizeFormat::Bytes => format!("{bytes} bytes"),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Points in time given on the command line, such as `30d` or `2024-01-31`.
+//!
+//! A relative duration like `30d`, `6h`, `2weeks` or `1y 6months` means that long before
+//! now. Absolute dates are `YYYY-MM-DD` (local midnight), `YYYY-MM-DD HH:MM[:SS]` (local
+//! time; a `T` may replace the space) or RFC 3339 with an explicit offset.
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+/// Layouts accepted for a local date and time.
+const DATE_TIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+];
+
+/// An absolute point in time, resolved when it is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(pub SystemTime);
+
+impl Timestamp {
+    /// The moment `duration` before now.
+    pub fn ago(duration: Duration) -> Option<Self> {
+        SystemTime::now().checked_sub(duration).map(Timestamp)
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+            return Ok(Timestamp(time.into()));
+        }
+        let naive = DATE_TIME_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+            .or_else(|| {
+                NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                    .ok()
+                    .map(|date| date.and_time(NaiveTime::MIN))
+            });
+        if let Some(naive) = naive {
+            // Ambiguous local times (DST fall-back) resolve to the earlier one
+            return Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|time| Timestamp(time.into()))
+                .ok_or_else(|| format!("'{s}' does not exist in the local time zone"));
+        }
+        match humantime::parse_duration(s) {
+            Ok(duration) => {
+                Timestamp::ago(duration).ok_or_else(|| format!("'{s}' reaches too far back"))
+            }
+            Err(_) => Err(format!(
+                "invalid time '{s}' (expected a duration such as 30d or 6h, or a date such as 2024-01-31)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time: DateTime<Local> = self.0.into();
+        write!(f, "{}", time.format("%Y-%m-%d %H:%M:%S"))
+    }
+}
@@ -0,0 +1,222 @@
+//! Recoverable alternatives to deleting a file: the freedesktop.org trash and a
+//! quarantine directory that mirrors the original directory tree.
+
+use chrono::Local;
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+};
+
+/// Moves `path` into the trash following the freedesktop.org Trash specification.
+///
+/// Files on the same filesystem as the home trash (`$XDG_DATA_HOME/Trash`) go there;
+/// files on other filesystems go to `$topdir/.Trash-$uid` at the root of their mount,
+/// so the move is always a cheap rename. A matching `.trashinfo` file records the
+/// original location, which lets desktop file managers restore it as well.
+///
+/// # Returns
+///
+/// * `io::Result<PathBuf>` - The file's new location inside the trash.
+#[cfg(unix)]
+pub fn trash_file(path: &Path) -> io::Result<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+
+    let absolute = std::path::absolute(path)?;
+    let device = fs::symlink_metadata(&absolute)?.dev();
+
+    let home_trash = home_trash_dir()?;
+    create_private_dir(&home_trash)?;
+    let (trash_dir, info_path) = if fs::metadata(&home_trash)?.dev() == device {
+        // Home trash: the info file holds the absolute path
+        (home_trash, absolute.clone())
+    } else {
+        // Top directory trash: the info file holds the path relative to the mount point
+        let topdir = mount_point(&absolute, device);
+        // SAFETY: getuid has no preconditions and cannot fail.
+        let uid = unsafe { libc::getuid() };
+        let relative = absolute
+            .strip_prefix(&topdir)
+            .unwrap_or(&absolute)
+            .to_path_buf();
+        (topdir.join(format!(".Trash-{uid}")), relative)
+    };
+
+    let files_dir = trash_dir.join("files");
+    let info_dir = trash_dir.join("info");
+    create_private_dir(&files_dir)?;
+    create_private_dir(&info_dir)?;
+
+    // Reserve a unique name by creating its info file exclusively, as the spec requires
+    let file_name = absolute
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let (name, mut info_file) = reserve_trash_name(&info_dir, file_name)?;
+    let trashed = files_dir.join(&name);
+
+    writeln!(info_file, "[Trash Info]")?;
+    writeln!(info_file, "Path={}", percent_encode(&info_path))?;
+    writeln!(
+        info_file,
+        "DeletionDate={}",
+        Local::now().format("%Y-%m-%dT%H:%M:%S")
+    )?;
+    drop(info_file);
+
+    if let Err(e) = fs::rename(&absolute, &trashed) {
+        let mut info_name = name;
+        info_name.push(".trashinfo");
+        let _ = fs::remove_file(info_dir.join(info_name));
+        return Err(e);
+    }
+    Ok(trashed)
+}
+
+/// The trash is only implemented for freedesktop.org (Unix) systems.
+#[cfg(not(unix))]
+pub fn trash_file(_path: &Path) -> io::Result<PathBuf> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the freedesktop.org trash is not available on this platform",
+    ))
+}
+
+/// Moves `path` into `quarantine_dir`, mirroring its absolute directory structure.
+///
+/// For example `/home/me/photos/a.jpg` is moved to `<quarantine_dir>/home/me/photos/a.jpg`.
+/// If that destination already exists, a numeric suffix is appended. Moves across
+/// filesystems fall back to copy-and-remove.
+///
+/// # Returns
+///
+/// * `io::Result<PathBuf>` - The file's new location inside the quarantine directory.
+pub fn quarantine_file(path: &Path, quarantine_dir: &Path) -> io::Result<PathBuf> {
+    let absolute = std::path::absolute(path)?;
+    let relative: PathBuf = absolute
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect();
+
+    let mut destination = quarantine_dir.join(&relative);
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut counter = 1;
+    while destination.symlink_metadata().is_ok() {
+        counter += 1;
+        let mut name = relative.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{counter}"));
+        destination.set_file_name(name);
+    }
+
+    move_file(&absolute, &destination)?;
+    Ok(destination)
+}
+
+/// Moves a file, copying it when source and destination are on different filesystems.
+///
+/// The copy keeps the original permissions and modification time. If anything fails
+/// after the copy was made, the copy is removed and the original left in place.
+pub fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            let metadata = fs::metadata(from)?;
+            fs::copy(from, to)?;
+            let finish = || {
+                // The owner may set times through a read-only handle, and fs::copy has
+                // already given the copy the original's possibly read-only mode
+                if let Ok(modified) = metadata.modified() {
+                    File::open(to)?.set_modified(modified)?;
+                }
+                fs::set_permissions(to, metadata.permissions())?;
+                fs::remove_file(from)
+            };
+            finish().inspect_err(|_| {
+                let _ = fs::remove_file(to);
+            })
+        }
+        result => result,
+    }
+}
+
+/// Creates `dir` and any missing parents with mode 0700, as the trash specification
+/// requires, so other users cannot list what was trashed. Existing directories are left
+/// as they are.
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+}
+
+/// `$XDG_DATA_HOME/Trash`, falling back to `$HOME/.local/share/Trash`.
+#[cfg(unix)]
+fn home_trash_dir() -> io::Result<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .map(|data| data.join("Trash"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "cannot locate the home trash"))
+}
+
+/// Walks up from `path` to the highest ancestor still on `device`.
+#[cfg(unix)]
+fn mount_point(path: &Path, device: u64) -> PathBuf {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut topdir = path.to_path_buf();
+    for ancestor in path.ancestors().skip(1) {
+        match fs::metadata(ancestor) {
+            Ok(metadata) if metadata.dev() == device => topdir = ancestor.to_path_buf(),
+            _ => break,
+        }
+    }
+    topdir
+}
+
+/// Creates `<info_dir>/<name>.trashinfo` exclusively, trying `name.2`, `name.3`, ...
+/// until an unused name is found.
+#[cfg(unix)]
+fn reserve_trash_name(
+    info_dir: &Path,
+    file_name: &std::ffi::OsStr,
+) -> io::Result<(std::ffi::OsString, File)> {
+    let mut counter = 1;
+    loop {
+        let mut name = file_name.to_os_string();
+        if counter > 1 {
+            name.push(format!(".{counter}"));
+        }
+        let mut info_name = name.clone();
+        info_name.push(".trashinfo");
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(info_dir.join(&info_name))
+        {
+            Ok(file) => return Ok((name, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => counter += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Percent-encodes a path for the `Path=` key of a `.trashinfo` file.
+#[cfg(unix)]
+fn percent_encode(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
@@ -1,4 +1,5 @@
 use colored::*;
+use std::path::PathBuf;
 
 /// Displays the HashLaser banner in a stylish format.
 pub fn print_banner() {
@@ -41,9 +42,9 @@ pub fn print_info(message: &str) {
     println!("{} {}", "ℹ️ ".cyan(), message.cyan());
 }
 
-/// Prints a list of items with bullets.
-pub fn print_list(items: &[String]) {
+/// Prints a list of paths with bullets.
+pub fn print_list(items: &[PathBuf]) {
     for item in items {
-        println!("{} {}", "•".bright_blue(), item);
+        println!("{} {}", "•".bright_blue(), item.display());
     }
 }
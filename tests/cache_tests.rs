@@ -0,0 +1,74 @@
+use intelligent_file_deduplicator::cache::{FileStamp, HashCache};
+use intelligent_file_deduplicator::filter::FilterOptions;
+use intelligent_file_deduplicator::hashing::HashAlgorithm;
+use intelligent_file_deduplicator::scanner::{ScanOptions, scan_directory};
+use std::{
+    fs::{self, File},
+    io::Write,
+    sync::Arc,
+};
+use tempfile::tempdir;
+
+#[test]
+fn test_cache_round_trip_and_prune() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("data.txt");
+    File::create(&file_path)
+        .unwrap()
+        .write_all(b"cached")
+        .unwrap();
+    let key = file_path.to_str().unwrap();
+    let stamp = FileStamp::from_metadata(&fs::metadata(&file_path).unwrap());
+
+    let cache_path = dir.path().join("cache").join("hashes.bin");
+    let cache = HashCache::load(&cache_path).unwrap();
+    cache.insert(key, HashAlgorithm::Sha256, stamp, "abc".to_string());
+    cache.save().unwrap();
+
+    let reloaded = HashCache::load(&cache_path).unwrap();
+    assert_eq!(
+        reloaded.get(key, HashAlgorithm::Sha256, &stamp),
+        Some("abc".to_string())
+    );
+    assert_eq!(reloaded.get(key, HashAlgorithm::Blake3, &stamp), None);
+
+    // A different size invalidates the entry
+    let grown = FileStamp {
+        size: stamp.size + 1,
+        ..stamp
+    };
+    assert_eq!(reloaded.get(key, HashAlgorithm::Sha256, &grown), None);
+
+    // Removing the file makes the entry prunable
+    fs::remove_file(&file_path).unwrap();
+    assert_eq!(reloaded.prune(), 1);
+    assert_eq!(reloaded.stats().entries, 0);
+}
+
+#[test]
+fn test_second_scan_uses_cache() {
+    let dir = tempdir().unwrap();
+    let data = dir.path().join("data");
+    fs::create_dir(&data).unwrap();
+    for name in ["a.txt", "b.txt"] {
+        File::create(data.join(name))
+            .unwrap()
+            .write_all(b"same bytes")
+            .unwrap();
+    }
+
+    let options = ScanOptions {
+        cache: Some(Arc::new(
+            HashCache::load(dir.path().join("hashes.bin")).unwrap(),
+        )),
+        ..ScanOptions::default()
+    };
+    let root = data.to_str().unwrap();
+
+    let first = scan_directory(root, &FilterOptions::default(), &options);
+    assert_eq!(first.stats.cache_hits, 0);
+
+    let second = scan_directory(root, &FilterOptions::default(), &options);
+    assert_eq!(second.stats.cache_hits, 2);
+    assert_eq!(first.duplicates, second.duplicates);
+}
@@ -0,0 +1,147 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::tempdir;
+
+/// The binary with its cache and journals redirected into `home`.
+fn hashlaser(home: &std::path::Path) -> Command {
+    let mut cmd = Command::cargo_bin("intelligent-file-deduplicator").unwrap();
+    cmd.env("XDG_CACHE_HOME", home.join("cache"))
+        .env("XDG_DATA_HOME", home.join("data"));
+    cmd
+}
+
+#[test]
+fn test_invalid_values_are_rejected_before_anything_runs() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path().join("files");
+    fs::create_dir(&dir).unwrap();
+    fs::write(dir.join("a.txt"), "same").unwrap();
+    fs::write(dir.join("b.txt"), "same").unwrap();
+
+    // A broken --regex once meant "no filter" and deleted everything
+    hashlaser(temp.path())
+        .args(["delete", dir.to_str().unwrap(), "--regex", "("])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid value '(' for '--name-regex",
+        ));
+    hashlaser(temp.path())
+        .args(["delete", dir.to_str().unwrap(), "--path-glob", "a/[b"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid value 'a/[b' for '--path-glob",
+        ));
+    hashlaser(temp.path())
+        .args(["delete", dir.to_str().unwrap(), "--where", "ext = txt or"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("at column 13"));
+    hashlaser(temp.path())
+        .args(["delete", dir.to_str().unwrap(), "--min", "abc"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'abc' for '--min"));
+    hashlaser(temp.path())
+        .args(["scan", dir.to_str().unwrap(), "--min", "10", "--max", "5"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--min (10 B) is larger than --max (5 B)",
+        ));
+
+    assert!(dir.join("a.txt").exists());
+    assert!(dir.join("b.txt").exists());
+}
+
+#[test]
+fn test_root_options_must_name_a_scanned_directory() {
+    let temp = tempdir().unwrap();
+    let (a, b) = (temp.path().join("a"), temp.path().join("b"));
+    fs::create_dir(&a).unwrap();
+    fs::create_dir(&b).unwrap();
+    fs::write(a.join("x.txt"), "same").unwrap();
+    fs::write(b.join("x.txt"), "same").unwrap();
+
+    hashlaser(temp.path())
+        .args(["delete", a.to_str().unwrap(), "--only-from"])
+        .arg(&b)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "is not one of the directories being scanned",
+        ));
+    hashlaser(temp.path())
+        .args(["scan", a.to_str().unwrap(), "--cross-root"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("needs at least two directories"));
+    assert!(b.join("x.txt").exists());
+
+    hashlaser(temp.path())
+        .args(["delete", "--no-cache"])
+        .args([&a, &b])
+        .arg("--only-from")
+        .arg(&b)
+        .assert()
+        .success();
+    assert!(a.join("x.txt").exists());
+    assert!(!b.join("x.txt").exists());
+}
+
+#[test]
+fn test_misspelled_flags_get_suggestions() {
+    let temp = tempdir().unwrap();
+    hashlaser(temp.path())
+        .args(["delete", ".", "--dry-rn"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "a similar argument exists: '--dry-run'",
+        ));
+    hashlaser(temp.path())
+        .args(["link", ".", "--reflink", "--symlink"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn test_each_subcommand_has_help() {
+    let temp = tempdir().unwrap();
+    hashlaser(temp.path())
+        .args(["link", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--relative"))
+        .stdout(predicate::str::contains("--keep <RULES>"))
+        .stdout(predicate::str::contains("--trash").not());
+}
+
+#[test]
+fn test_restore_works_from_another_directory() {
+    let temp = tempdir().unwrap();
+    let work = temp.path().join("work");
+    fs::create_dir_all(work.join("a")).unwrap();
+    fs::write(work.join("a/x"), "same").unwrap();
+    fs::write(work.join("a/y"), "same").unwrap();
+    let journal = temp.path().join("journal.jsonl");
+
+    // Relative paths on the command line, as a user would type them
+    hashlaser(temp.path())
+        .current_dir(&work)
+        .args(["delete", "a", "--journal", journal.to_str().unwrap()])
+        .assert()
+        .success();
+    assert!(!work.join("a/y").exists());
+
+    hashlaser(temp.path())
+        .current_dir(temp.path())
+        .args(["restore", journal.to_str().unwrap()])
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(work.join("a/y")).unwrap(), "same");
+    assert!(!temp.path().join("a").exists());
+}
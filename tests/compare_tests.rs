@@ -1,4 +1,4 @@
-use intelligent_file_deduplicator::file_compare::compare_files;
+use intelligent_file_deduplicator::file_compare::{compare_files, files_identical};
 use std::io::Write;
 use tempfile::NamedTempFile;
 
@@ -31,3 +31,24 @@ fn test_compare_different_files() {
     let result = compare_files(path1, path2).unwrap();
     assert!(!result, "Files should be different");
 }
+
+#[test]
+fn test_byte_comparison() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    let mut file2 = NamedTempFile::new().unwrap();
+    let mut file3 = NamedTempFile::new().unwrap();
+
+    let content = vec![7u8; 200_000];
+    file1.write_all(&content).unwrap();
+    file2.write_all(&content).unwrap();
+    let mut changed = content.clone();
+    changed[150_000] = 8;
+    file3.write_all(&changed).unwrap();
+
+    let path1 = file1.path().to_str().unwrap();
+    let path2 = file2.path().to_str().unwrap();
+    let path3 = file3.path().to_str().unwrap();
+
+    assert!(files_identical(path1, path2).unwrap());
+    assert!(!files_identical(path1, path3).unwrap());
+}
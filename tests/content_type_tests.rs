@@ -0,0 +1,64 @@
+use intelligent_file_deduplicator::content_type::{self, MediaKind, MimePattern};
+use intelligent_file_deduplicator::filter::FilterOptions;
+use std::fs;
+use tempfile::tempdir;
+
+const JPEG: &[u8] = b"\xff\xd8\xff\xe0\x00\x10JFIF\x00";
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR";
+
+#[test]
+fn test_detects_type_from_content_not_name() {
+    let dir = tempdir().unwrap();
+    let misnamed = dir.path().join("photo.dat");
+    fs::write(&misnamed, JPEG).unwrap();
+    let found = content_type::detect(&misnamed).unwrap().unwrap();
+    assert_eq!(found.mime, "image/jpeg");
+    assert_eq!(found.kind, MediaKind::Image);
+
+    let text = dir.path().join("notes.jpg");
+    fs::write(&text, "just text").unwrap();
+    assert_eq!(content_type::detect(&text).unwrap(), None);
+    assert!(content_type::detect(&dir.path().join("missing")).is_err());
+}
+
+#[test]
+fn test_mime_patterns_and_kinds_parse() {
+    let pdf: MimePattern = "application/pdf".parse().unwrap();
+    assert!(pdf.matches("application/PDF"));
+    assert!(!pdf.matches("application/zip"));
+    let images: MimePattern = "image/*".parse().unwrap();
+    assert!(images.matches("image/png"));
+    assert!(!images.matches("video/mp4"));
+    assert!("pdf".parse::<MimePattern>().is_err());
+    assert!("image/".parse::<MimePattern>().is_err());
+
+    assert_eq!("Video".parse::<MediaKind>().unwrap(), MediaKind::Video);
+    assert!("picture".parse::<MediaKind>().is_err());
+}
+
+#[test]
+fn test_type_and_mime_filters() {
+    let dir = tempdir().unwrap();
+    let jpeg = dir.path().join("a.dat");
+    let png = dir.path().join("b.bin");
+    let text = dir.path().join("c.txt");
+    fs::write(&jpeg, JPEG).unwrap();
+    fs::write(&png, PNG).unwrap();
+    fs::write(&text, "text").unwrap();
+
+    let images = FilterOptions {
+        types: vec![MediaKind::Image],
+        ..FilterOptions::default()
+    };
+    assert!(images.matches(&jpeg));
+    assert!(images.matches(&png));
+    assert!(!images.matches(&text));
+
+    let only_png = FilterOptions {
+        types: vec![MediaKind::Image],
+        mimes: vec!["image/png".parse().unwrap()],
+        ..FilterOptions::default()
+    };
+    assert!(!only_png.matches(&jpeg));
+    assert!(only_png.matches(&png));
+}
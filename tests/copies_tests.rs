@@ -0,0 +1,74 @@
+use intelligent_file_deduplicator::copies::find_copies;
+use intelligent_file_deduplicator::error::DedupError;
+use intelligent_file_deduplicator::filter::FilterOptions;
+use intelligent_file_deduplicator::scanner::ScanOptions;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_finds_copies_of_each_reference() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    for sub in ["photos/2023", "backup", "other"] {
+        fs::create_dir_all(root.join(sub)).unwrap();
+    }
+    let reference = root.join("photos/2023/beach.jpg");
+    let lonely = root.join("lonely.txt");
+    fs::write(&reference, "sand and sea").unwrap();
+    fs::write(&lonely, "no copies of me").unwrap();
+    fs::write(root.join("backup/beach-copy.jpg"), "sand and sea").unwrap();
+    fs::write(root.join("other/renamed.dat"), "sand and sea").unwrap();
+    // Same size, different content: hashed, but not a copy
+    fs::write(root.join("other/impostor.jpg"), "sand and tea").unwrap();
+    fs::write(root.join("other/small.txt"), "tiny").unwrap();
+
+    let result = find_copies(
+        &[&reference, &lonely, &root.join("missing.jpg")],
+        &[root],
+        &FilterOptions::default(),
+        &ScanOptions::default(),
+    );
+
+    assert_eq!(result.files_scanned, 6);
+    // Neither reference is hashed again as a candidate, and small.txt not at all
+    assert_eq!(result.size_candidates, 3);
+    assert_eq!(result.matches.len(), 2);
+    assert_eq!(result.matches[0].reference, reference);
+    assert_eq!(
+        result.matches[0].copies,
+        [
+            root.join("backup/beach-copy.jpg"),
+            root.join("other/renamed.dat")
+        ]
+    );
+    assert!(result.matches[1].copies.is_empty());
+    assert!(matches!(
+        result.errors.as_slice(),
+        [DedupError::Vanished { path }] if path.ends_with("missing.jpg")
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_hardlinks_to_a_reference_are_not_copies() {
+    let dir = tempdir().unwrap();
+    let reference = dir.path().join("original.bin");
+    let link = dir.path().join("link.bin");
+    let copy = dir.path().join("copy.bin");
+    fs::write(&reference, "shared bytes").unwrap();
+    fs::hard_link(&reference, &link).unwrap();
+    fs::write(&copy, "shared bytes").unwrap();
+
+    let filters = FilterOptions {
+        extensions: Some(vec!["bin".to_string()]),
+        ..FilterOptions::default()
+    };
+    let result = find_copies(
+        &[&reference],
+        &[dir.path()],
+        &filters,
+        &ScanOptions::default(),
+    );
+    assert_eq!(result.matches[0].copies, [copy]);
+    assert_eq!(result.matches[0].hardlinks, [link]);
+}
@@ -1,5 +1,13 @@
-use intelligent_file_deduplicator::safe_delete::delete_duplicates;
-use std::{collections::HashMap, fs::File, io::Write};
+use intelligent_file_deduplicator::error::DedupError;
+use intelligent_file_deduplicator::journal::JournalAction;
+use intelligent_file_deduplicator::safe_delete::{
+    DeleteOptions, FileStatus, delete_duplicates, delete_duplicates_with,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::Write,
+};
 use tempfile::tempdir;
 
 #[test]
@@ -19,13 +27,7 @@ fn test_dry_run_deletion() {
         .unwrap();
 
     let mut duplicates = HashMap::new();
-    duplicates.insert(
-        "dummyhash".to_string(),
-        vec![
-            file1.to_string_lossy().to_string(),
-            file2.to_string_lossy().to_string(),
-        ],
-    );
+    duplicates.insert("dummyhash".to_string(), vec![file1.clone(), file2.clone()]);
 
     // Dry run (no file should be deleted)
     let result = delete_duplicates(&duplicates, true);
@@ -51,13 +53,7 @@ fn test_actual_deletion() {
         .unwrap();
 
     let mut duplicates = HashMap::new();
-    duplicates.insert(
-        "hashkey".to_string(),
-        vec![
-            file1.to_string_lossy().to_string(),
-            file2.to_string_lossy().to_string(),
-        ],
-    );
+    duplicates.insert("hashkey".to_string(), vec![file1.clone(), file2.clone()]);
 
     let result = delete_duplicates(&duplicates, false);
     assert!(result.is_ok());
@@ -66,3 +62,201 @@ fn test_actual_deletion() {
     let remaining = [file1.exists(), file2.exists()];
     assert_eq!(remaining.iter().filter(|&&exists| exists).count(), 1);
 }
+
+#[test]
+fn test_protected_file_is_kept() {
+    let dir = tempdir().unwrap();
+
+    let file1 = dir.path().join("a.txt");
+    let file2 = dir.path().join("b.txt");
+    for file in [&file1, &file2] {
+        File::create(file).unwrap().write_all(b"same").unwrap();
+    }
+
+    let mut duplicates = HashMap::new();
+    duplicates.insert("hash".to_string(), vec![file1.clone(), file2.clone()]);
+
+    // b.txt is a symlink target and must survive even though a.txt comes first
+    let options = DeleteOptions {
+        dry_run: false,
+        protected: HashSet::from([std::fs::canonicalize(&file2).unwrap()]),
+        ..DeleteOptions::default()
+    };
+    delete_duplicates_with(&duplicates, &options).unwrap();
+
+    assert!(!file1.exists());
+    assert!(file2.exists());
+}
+
+#[test]
+fn test_only_under_limits_deletion_to_those_directories() {
+    let dir = tempdir().unwrap();
+    let (original, backup) = (dir.path().join("original"), dir.path().join("backup"));
+    std::fs::create_dir_all(&original).unwrap();
+    std::fs::create_dir_all(&backup).unwrap();
+    let write = |path: &std::path::Path, content: &[u8]| {
+        File::create(path).unwrap().write_all(content).unwrap();
+        path.to_path_buf()
+    };
+    let shared = [
+        write(&original.join("a.txt"), b"shared"),
+        write(&original.join("b.txt"), b"shared"),
+        write(&backup.join("a.txt"), b"shared"),
+    ];
+    let only_original = [
+        write(&original.join("c.txt"), b"local"),
+        write(&original.join("d.txt"), b"local"),
+    ];
+
+    let mut duplicates = HashMap::new();
+    duplicates.insert("shared".to_string(), shared.to_vec());
+    duplicates.insert("local".to_string(), only_original.to_vec());
+    let options = DeleteOptions {
+        only_under: vec![backup.clone()],
+        ..DeleteOptions::default()
+    };
+    let report = delete_duplicates_with(&duplicates, &options).unwrap();
+
+    // Only the backup copy goes; the originals are never touched, not even among themselves
+    assert!(!shared[2].exists());
+    assert!(shared[0].exists() && shared[1].exists());
+    assert!(only_original.iter().all(|path| path.exists()));
+    assert!(report.is_complete());
+}
+
+#[test]
+fn test_mismatched_candidate_is_refused() {
+    let dir = tempdir().unwrap();
+
+    let keep = dir.path().join("keep.txt");
+    let impostor = dir.path().join("impostor.txt");
+    File::create(&keep).unwrap().write_all(b"original").unwrap();
+    File::create(&impostor)
+        .unwrap()
+        .write_all(b"different")
+        .unwrap();
+
+    // The grouping claims they match, but the bytes say otherwise
+    let mut duplicates = HashMap::new();
+    duplicates.insert(
+        "dummyhash".to_string(),
+        vec![keep.clone(), impostor.clone()],
+    );
+
+    let report = delete_duplicates(&duplicates, false).unwrap();
+    assert!(keep.exists());
+    assert!(impostor.exists());
+    assert!(!report.is_complete());
+    assert!(matches!(
+        report.outcomes[0].status,
+        FileStatus::Skipped(DedupError::ContentMismatch { .. })
+    ));
+
+    // Skipping verification is possible, but only when asked for
+    let options = DeleteOptions {
+        verify: false,
+        ..DeleteOptions::default()
+    };
+    let report = delete_duplicates_with(&duplicates, &options).unwrap();
+    assert!(keep.exists());
+    assert!(!impostor.exists());
+    assert!(report.is_complete());
+    assert!(matches!(
+        report.outcomes[0].status,
+        FileStatus::Done(JournalAction::Removed)
+    ));
+
+    // A file that vanished since the scan is reported as a per-file failure
+    let report = delete_duplicates_with(&duplicates, &options).unwrap();
+    assert!(matches!(
+        report.outcomes[0].status,
+        FileStatus::Failed(DedupError::Vanished { .. })
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_names_survive_scan_delete_and_journal() {
+    use intelligent_file_deduplicator::filter::FilterOptions;
+    use intelligent_file_deduplicator::journal::Journal;
+    use intelligent_file_deduplicator::scanner::scan_directory_for_duplicates;
+    use std::{ffi::OsString, os::unix::ffi::OsStringExt, path::PathBuf};
+
+    let dir = tempdir().unwrap();
+    // "café.txt" and "café-copie.txt" in Latin-1, plus a UTF-8 "café.txt" that must survive
+    let latin1 = dir.path().join(OsString::from_vec(b"caf\xe9.txt".to_vec()));
+    let copy = dir
+        .path()
+        .join(OsString::from_vec(b"caf\xe9-copie.txt".to_vec()));
+    let utf8 = dir.path().join("café.txt");
+    for file in [&latin1, &copy] {
+        File::create(file).unwrap().write_all(b"ancien").unwrap();
+    }
+    File::create(&utf8).unwrap().write_all(b"neuf").unwrap();
+
+    let duplicates = scan_directory_for_duplicates(dir.path(), &FilterOptions::default());
+    let group: Vec<PathBuf> = duplicates.values().next().unwrap().clone();
+    assert_eq!(group, vec![copy.clone(), latin1.clone()]);
+
+    let journal_path = dir.path().join("journal.json");
+    let options = DeleteOptions {
+        journal: Some(journal_path.clone()),
+        ..DeleteOptions::default()
+    };
+    let report = delete_duplicates_with(&duplicates, &options).unwrap();
+    assert!(report.is_complete());
+    assert!(copy.exists());
+    assert!(!latin1.exists());
+    assert!(utf8.exists());
+
+    let journal = Journal::read(&journal_path).unwrap();
+    assert_eq!(journal.entries[0].original, latin1);
+    assert_eq!(journal.entries[0].kept, copy);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_hardlinked_copy_is_never_removed() {
+    use intelligent_file_deduplicator::filter::FilterOptions;
+    use intelligent_file_deduplicator::scanner::{ScanOptions, scan_roots};
+
+    let dir = tempdir().unwrap();
+    let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+    std::fs::create_dir_all(&a).unwrap();
+    std::fs::create_dir_all(&b).unwrap();
+    std::fs::write(a.join("x"), "hello\n").unwrap();
+    std::fs::hard_link(a.join("x"), a.join("x_link")).unwrap();
+    std::fs::write(b.join("y"), "hello\n").unwrap();
+
+    let scan = scan_roots(
+        &[a.clone(), b.clone()],
+        &FilterOptions::default(),
+        &ScanOptions::default(),
+    );
+    assert_eq!(scan.duplicates.len(), 1);
+    // Only b/y frees space; a/x is still held by a/x_link
+    assert_eq!(scan.stats.reclaimable_bytes, 6);
+
+    let options = DeleteOptions {
+        keep: format!("prefer:{}", b.display()).parse().unwrap(),
+        ..DeleteOptions::default()
+    };
+    let report = delete_duplicates_with(&scan.duplicates, &options).unwrap();
+    assert!(report.is_complete());
+    assert!(a.join("x").exists());
+    assert!(a.join("x_link").exists());
+    assert!(!b.join("y").exists());
+
+    // Once every copy has further links, nothing can be freed
+    std::fs::write(b.join("y"), "hello\n").unwrap();
+    std::fs::hard_link(b.join("y"), b.join("y_link")).unwrap();
+    let scan = scan_roots(
+        &[a, b.clone()],
+        &FilterOptions::default(),
+        &ScanOptions::default(),
+    );
+    assert_eq!(scan.stats.reclaimable_bytes, 0);
+    let report = delete_duplicates_with(&scan.duplicates, &DeleteOptions::default()).unwrap();
+    assert!(matches!(report.outcomes[0].status, FileStatus::Protected));
+    assert!(b.join("y").exists());
+}
@@ -0,0 +1,126 @@
+use intelligent_file_deduplicator::expr::Expr;
+use std::{fs, path::Path};
+use tempfile::tempdir;
+
+#[test]
+fn test_expression_precedence_and_evaluation() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join("photos")).unwrap();
+    let big = root.join("photos/big.JPG");
+    let small = root.join("photos/small.png");
+    let backup = root.join("notes-backup.txt");
+    let other = root.join("notes.txt");
+    fs::write(&big, vec![0u8; 2048]).unwrap();
+    fs::write(&small, "tiny").unwrap();
+    fs::write(&backup, "text").unwrap();
+    fs::write(&other, "text").unwrap();
+
+    let matches = |expr: &str, path: &Path| {
+        let expr: Expr = expr.parse().unwrap();
+        expr.matches(path, path.strip_prefix(root).unwrap())
+    };
+
+    let expr = "(ext in [jpg, png] and size > 1KiB) or name ~ 'backup'";
+    assert!(matches(expr, &big));
+    assert!(!matches(expr, &small));
+    assert!(matches(expr, &backup));
+    assert!(!matches(expr, &other));
+
+    // `and` binds tighter than `or`, and `not` tighter than both
+    let expr = "ext = txt or ext = png and size > 1KiB";
+    assert!(matches(expr, &other));
+    assert!(!matches(expr, &small));
+    assert!(matches("not ext = txt and size < 1k", &small));
+    assert!(!matches("not (ext = txt or size < 1k)", &small));
+
+    // Paths are relative to the root; keywords and fields are case-insensitive
+    assert!(matches("path ~ '^photos/' AND NOT name != big.JPG", &big));
+    assert!(matches(
+        "modified > 1h and modified <= '2999-01-01'",
+        &other
+    ));
+    assert!(!matches("size = 0", &other));
+}
+
+#[test]
+fn test_parse_errors_point_at_the_column() {
+    let column = |expr: &str| expr.parse::<Expr>().unwrap_err().column;
+
+    assert_eq!(column("size > 1MiBB"), 8);
+    assert_eq!(column("colour = red"), 1);
+    assert_eq!(column("size ~ 10"), 6);
+    assert_eq!(column("ext in [jpg png]"), 13);
+    assert_eq!(column("(name = a"), 10);
+    assert_eq!(column("name = a b"), 10);
+    assert_eq!(column("name ~ '(' "), 8);
+    assert_eq!(column("name = 'open"), 8);
+    assert_eq!(column("size > "), 8);
+
+    let error = "ext = jpg and".parse::<Expr>().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "expected a field (name, path, ext, size, modified, accessed, created, owner or group), \
+         found the end of the expression at column 14\n  ext = jpg and\n               ^"
+    );
+}
+
+#[test]
+fn test_filter_options_build_one_expression() {
+    use intelligent_file_deduplicator::filter::FilterOptions;
+
+    let dir = tempdir().unwrap();
+    let big = dir.path().join("big.txt");
+    let small = dir.path().join("small.txt");
+    let other = dir.path().join("big.log");
+    fs::write(&big, "a larger file").unwrap();
+    fs::write(&small, "tiny").unwrap();
+    fs::write(&other, "a larger file").unwrap();
+
+    assert!(FilterOptions::default().to_expr().is_none());
+
+    let filters = FilterOptions {
+        extensions: Some(vec!["txt".to_string()]),
+        expression: Some("size > 8 or name = 'small.txt'".parse().unwrap()),
+        ..FilterOptions::default()
+    };
+    // The flag and the expression are one tree: `ext in [txt] and (size > 8 or ...)`
+    let expr = filters.to_expr().unwrap();
+    assert!(matches!(expr, Expr::And(..)));
+    for (path, expected) in [(&big, true), (&small, true), (&other, false)] {
+        assert_eq!(expr.matches(path, path), expected, "{}", path.display());
+        assert_eq!(filters.matches(path), expected, "{}", path.display());
+    }
+
+    // Unreadable files pass option tests, so the scan can report them
+    let missing = dir.path().join("missing.txt");
+    let sized = FilterOptions {
+        min_size: Some(1),
+        ..FilterOptions::default()
+    };
+    assert!(sized.matches(&missing));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_owner_inequality_rejects_unreadable_files() {
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("mine.txt");
+    let missing = dir.path().join("missing.txt");
+    fs::write(&file, "text").unwrap();
+    let metadata = fs::metadata(&file).unwrap();
+    let other_uid = metadata.uid() + 1;
+    let other_gid = metadata.gid() + 1;
+
+    for source in [
+        format!("owner != {other_uid}"),
+        format!("group != {other_gid}"),
+    ] {
+        let expr: Expr = source.parse().unwrap();
+        assert!(expr.matches(&file, &file), "{source}");
+        // An unknown owner is not known to differ
+        assert!(!expr.matches(&missing, &missing), "{source}");
+    }
+}
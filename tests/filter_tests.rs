@@ -23,6 +23,7 @@ fn test_filter_options_match() {
         max_size: Some(file_size + 10),
         extensions: Some(vec!["txt".to_string()]),
         regex: Some(Regex::new(r".*log.*").unwrap()),
+        ..FilterOptions::default()
     };
 
     assert!(options.matches(Path::new(&file_path)));
@@ -41,7 +42,111 @@ fn test_filter_excludes_large_file() {
         max_size: Some(5000),
         extensions: None,
         regex: None,
+        ..FilterOptions::default()
     };
 
     assert!(!options.matches(Path::new(&file_path)));
 }
+
+#[test]
+fn test_exclude_patterns_dirs_and_extensions() {
+    let options = FilterOptions {
+        exclude: vec!["*.tmp".parse().unwrap(), "target/".parse().unwrap()],
+        exclude_dirs: vec!["node_modules".to_string(), "assets/cache".to_string()],
+        exclude_extensions: vec!["BAK".to_string()],
+        ..FilterOptions::default()
+    };
+
+    // Nothing here exists, so only the exclusions can reject these paths
+    assert!(!options.matches(Path::new("project/notes.tmp")));
+    assert!(!options.matches(Path::new("project/notes.bak")));
+    assert!(options.matches(Path::new("project/notes.txt")));
+    // `target/` only applies to directories
+    assert!(options.matches(Path::new("project/target")));
+
+    assert!(options.excludes_dir(Path::new("project/target")));
+    assert!(options.excludes_dir(Path::new("project/web/node_modules")));
+    assert!(options.excludes_dir(Path::new("project/assets/cache")));
+    assert!(!options.excludes_dir(Path::new("project/cache")));
+    assert!(!options.excludes_dir(Path::new("project/node_modules_old")));
+}
+
+#[test]
+fn test_time_filters() {
+    use intelligent_file_deduplicator::filter::{TimeBound, TimeField, TimeFilter};
+    use std::fs::FileTimes;
+    use std::time::{Duration, SystemTime};
+
+    let dir = tempdir().unwrap();
+    let old = dir.path().join("old.txt");
+    let new = dir.path().join("new.txt");
+    fs::write(&old, "data").unwrap();
+    fs::write(&new, "data").unwrap();
+    let two_years_ago = SystemTime::now() - Duration::from_secs(2 * 365 * 86_400);
+    File::options()
+        .write(true)
+        .open(&old)
+        .unwrap()
+        .set_times(
+            FileTimes::new()
+                .set_modified(two_years_ago)
+                .set_accessed(two_years_ago),
+        )
+        .unwrap();
+
+    let one_year_ago = SystemTime::now() - Duration::from_secs(365 * 86_400);
+    let untouched = FilterOptions {
+        times: vec![
+            TimeFilter {
+                field: TimeField::Modified,
+                bound: TimeBound::Before(one_year_ago),
+            },
+            TimeFilter {
+                field: TimeField::Accessed,
+                bound: TimeBound::Before(one_year_ago),
+            },
+        ],
+        ..FilterOptions::default()
+    };
+    assert!(untouched.matches(&old));
+    assert!(!untouched.matches(&new));
+
+    let recent = FilterOptions {
+        times: vec![TimeFilter {
+            field: TimeField::Modified,
+            bound: TimeBound::After(one_year_ago),
+        }],
+        ..FilterOptions::default()
+    };
+    assert!(!recent.matches(&old));
+    assert!(recent.matches(&new));
+}
+
+#[test]
+fn test_path_patterns_match_relative_to_the_root() {
+    use globset::Glob;
+
+    let root = Path::new("/srv/share");
+    let file = root.join("clients/exports/2023/q1.csv");
+    let options = FilterOptions {
+        path_glob: Some(Glob::new("*/exports/2023/*").unwrap().compile_matcher()),
+        ..FilterOptions::default()
+    };
+    assert!(options.matches_in(&file, root));
+    assert!(!options.matches_in(&root.join("clients/exports/2024/q1.csv"), root));
+
+    // Anchored at the scan root, not at the filesystem root
+    let options = FilterOptions {
+        path_regex: Some(Regex::new(r"^clients/.*\.csv$").unwrap()),
+        ..FilterOptions::default()
+    };
+    assert!(options.matches_in(&file, root));
+    assert!(!options.matches_in(&file, Path::new("/srv")));
+
+    // The name regex still sees only the file name
+    let options = FilterOptions {
+        regex: Some(Regex::new(r"^q1\.csv$").unwrap()),
+        ..FilterOptions::default()
+    };
+    assert!(options.matches_in(&file, root));
+}
@@ -1,4 +1,6 @@
-use intelligent_file_deduplicator::hashing::hash_file;
+use intelligent_file_deduplicator::hashing::{
+    HashAlgorithm, hash_file, hash_file_sample, hash_file_with,
+};
 use std::io::Write;
 use tempfile::NamedTempFile;
 
@@ -13,3 +15,42 @@ fn test_hash_consistency() {
 
     assert_eq!(hash1, hash2);
 }
+
+#[test]
+fn test_sample_hash_ignores_middle_bytes() {
+    let mut a = NamedTempFile::new().unwrap();
+    let mut b = NamedTempFile::new().unwrap();
+
+    let mut content = vec![b'x'; 10_000];
+    a.write_all(&content).unwrap();
+    content[5_000] = b'y';
+    b.write_all(&content).unwrap();
+
+    let path_a = a.path().to_str().unwrap();
+    let path_b = b.path().to_str().unwrap();
+
+    assert_eq!(
+        hash_file_sample(path_a, 1024, HashAlgorithm::Sha256).unwrap(),
+        hash_file_sample(path_b, 1024, HashAlgorithm::Sha256).unwrap()
+    );
+    assert_ne!(hash_file(path_a).unwrap(), hash_file(path_b).unwrap());
+}
+
+#[test]
+fn test_algorithms_produce_distinct_digests() {
+    let mut temp = NamedTempFile::new().unwrap();
+    writeln!(temp, "HashLaser").unwrap();
+    let path = temp.path().to_str().unwrap();
+
+    let sha = hash_file_with(path, HashAlgorithm::Sha256).unwrap();
+    let blake = hash_file_with(path, HashAlgorithm::Blake3).unwrap();
+    let xxh = hash_file_with(path, HashAlgorithm::Xxh3).unwrap();
+
+    assert_eq!(sha, hash_file(path).unwrap());
+    assert_eq!(sha.len(), 64);
+    assert_eq!(blake.len(), 64);
+    assert_eq!(xxh.len(), 32);
+    assert_ne!(sha, blake);
+    assert_eq!("BLAKE3".parse::<HashAlgorithm>(), Ok(HashAlgorithm::Blake3));
+    assert!("md5".parse::<HashAlgorithm>().is_err());
+}
@@ -0,0 +1,238 @@
+use intelligent_file_deduplicator::hashing::HashAlgorithm;
+use intelligent_file_deduplicator::hashing::hash_file;
+use intelligent_file_deduplicator::journal::{
+    Journal, JournalAction, JournalEntry, restore_journal,
+};
+use intelligent_file_deduplicator::safe_delete::{
+    DeleteMode, DeleteOptions, delete_duplicates_with,
+};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+use tempfile::tempdir;
+
+fn duplicate_pair(
+    dir: &Path,
+) -> (
+    std::path::PathBuf,
+    std::path::PathBuf,
+    HashMap<String, Vec<std::path::PathBuf>>,
+) {
+    let keep = dir.join("a.txt");
+    let copy = dir.join("sub").join("b.txt");
+    fs::create_dir_all(copy.parent().unwrap()).unwrap();
+    for file in [&keep, &copy] {
+        File::create(file).unwrap().write_all(b"twin").unwrap();
+    }
+    let hash = hash_file(keep.to_str().unwrap()).unwrap();
+    let mut duplicates = HashMap::new();
+    duplicates.insert(hash, vec![keep.clone(), copy.clone()]);
+    (keep, copy, duplicates)
+}
+
+#[test]
+fn test_restore_removed_file_with_metadata() {
+    let dir = tempdir().unwrap();
+    let (keep, copy, duplicates) = duplicate_pair(dir.path());
+
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    File::options()
+        .write(true)
+        .open(&copy)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&copy, fs::Permissions::from_mode(0o640)).unwrap();
+    }
+
+    let journal_path = dir.path().join("journal.jsonl");
+    let options = DeleteOptions {
+        journal: Some(journal_path.clone()),
+        ..DeleteOptions::default()
+    };
+    delete_duplicates_with(&duplicates, &options).unwrap();
+    assert!(!copy.exists());
+
+    let journal = Journal::read(&journal_path).unwrap();
+    assert_eq!(journal.entries[0].action, JournalAction::Removed);
+    assert_eq!(journal.entries[0].kept, keep);
+    assert_eq!(journal.entries[0].size, 4);
+
+    let outcomes = restore_journal(&journal);
+    assert!(outcomes.iter().all(|outcome| outcome.result.is_ok()));
+    assert_eq!(fs::read(&copy).unwrap(), b"twin");
+    let metadata = fs::metadata(&copy).unwrap();
+    assert_eq!(metadata.modified().unwrap(), modified);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+    }
+
+    // Restoring twice never overwrites the file that is now back in place
+    let again = restore_journal(&journal);
+    assert!(again[0].result.is_err());
+}
+
+#[test]
+fn test_restore_quarantined_file() {
+    let dir = tempdir().unwrap();
+    let (_keep, copy, duplicates) = duplicate_pair(dir.path());
+
+    let journal_path = dir.path().join("journal.jsonl");
+    let options = DeleteOptions {
+        mode: DeleteMode::Quarantine(dir.path().join("q")),
+        journal: Some(journal_path.clone()),
+        ..DeleteOptions::default()
+    };
+    delete_duplicates_with(&duplicates, &options).unwrap();
+    assert!(!copy.exists());
+
+    let journal = Journal::read(&journal_path).unwrap();
+    let moved_to = journal.entries[0].moved_to.clone().unwrap();
+    restore_journal(&journal);
+
+    assert_eq!(fs::read(&copy).unwrap(), b"twin");
+    assert!(!Path::new(&moved_to).exists());
+}
+
+#[test]
+fn test_unwritable_journal_stops_before_deleting() {
+    let dir = tempdir().unwrap();
+    let (_keep, copy, duplicates) = duplicate_pair(dir.path());
+    File::create(dir.path().join("notadir")).unwrap();
+
+    let options = DeleteOptions {
+        journal: Some(dir.path().join("notadir").join("journal.jsonl")),
+        ..DeleteOptions::default()
+    };
+    assert!(delete_duplicates_with(&duplicates, &options).is_err());
+    assert!(copy.exists());
+}
+
+#[test]
+fn test_journal_removed_when_nothing_was_touched() {
+    let dir = tempdir().unwrap();
+    let (keep, copy, _) = duplicate_pair(dir.path());
+    fs::write(&copy, b"twine").unwrap();
+    let mut duplicates = HashMap::new();
+    duplicates.insert("stale".to_string(), vec![keep, copy.clone()]);
+
+    let journal_path = dir.path().join("journal.jsonl");
+    let options = DeleteOptions {
+        journal: Some(journal_path.clone()),
+        ..DeleteOptions::default()
+    };
+    let report = delete_duplicates_with(&duplicates, &options).unwrap();
+    assert!(!report.is_complete());
+    assert!(report.journal.is_none());
+    assert!(!journal_path.exists());
+    assert!(copy.exists());
+}
+
+#[test]
+fn test_restore_reflinked_file_rewrites_it() {
+    let dir = tempdir().unwrap();
+    let (keep, copy, duplicates) = duplicate_pair(dir.path());
+    let hash = duplicates.keys().next().unwrap().clone();
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    File::options()
+        .write(true)
+        .open(&copy)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+
+    let entry = JournalEntry::new(
+        JournalAction::Reflinked,
+        &copy,
+        &keep,
+        &hash,
+        &fs::metadata(&copy).unwrap(),
+    );
+    let journal = Journal {
+        created: String::new(),
+        algorithm: HashAlgorithm::default(),
+        entries: vec![entry],
+    };
+    #[cfg(unix)]
+    let inode = {
+        use std::os::unix::fs::MetadataExt;
+        fs::metadata(&copy).unwrap().ino()
+    };
+
+    let outcomes = restore_journal(&journal);
+    assert!(outcomes[0].result.is_ok());
+    assert_eq!(fs::read(&copy).unwrap(), b"twin");
+    assert_eq!(fs::metadata(&copy).unwrap().modified().unwrap(), modified);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        assert_ne!(fs::metadata(&copy).unwrap().ino(), inode);
+    }
+
+    // A file that changed since it was cloned is not rewritten
+    fs::write(&copy, b"edited").unwrap();
+    assert!(restore_journal(&journal)[0].result.is_err());
+    assert_eq!(fs::read(&copy).unwrap(), b"edited");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_restore_read_only_files() {
+    use std::os::unix::fs::PermissionsExt;
+
+    for mode in [
+        DeleteMode::Remove,
+        DeleteMode::Quarantine(Path::new("q").into()),
+    ] {
+        let dir = tempdir().unwrap();
+        let (_keep, copy, duplicates) = duplicate_pair(dir.path());
+        fs::set_permissions(&copy, fs::Permissions::from_mode(0o444)).unwrap();
+        let mode = match mode {
+            DeleteMode::Quarantine(q) => DeleteMode::Quarantine(dir.path().join(q)),
+            mode => mode,
+        };
+
+        let journal_path = dir.path().join("journal.jsonl");
+        let options = DeleteOptions {
+            mode,
+            journal: Some(journal_path.clone()),
+            ..DeleteOptions::default()
+        };
+        delete_duplicates_with(&duplicates, &options).unwrap();
+        assert!(!copy.exists());
+
+        let outcomes = restore_journal(&Journal::read(&journal_path).unwrap());
+        assert!(outcomes[0].result.is_ok(), "{:?}", outcomes[0].result);
+        let metadata = fs::metadata(&copy).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o444);
+    }
+}
+
+#[test]
+fn test_existing_journal_is_never_overwritten() {
+    let dir = tempdir().unwrap();
+    let (_keep, copy, duplicates) = duplicate_pair(dir.path());
+    let journal_path = dir.path().join("journal.jsonl");
+    let options = DeleteOptions {
+        journal: Some(journal_path.clone()),
+        ..DeleteOptions::default()
+    };
+    delete_duplicates_with(&duplicates, &options).unwrap();
+    let first = fs::read(&journal_path).unwrap();
+
+    // A second run with the same path stops before touching anything
+    fs::write(&copy, b"twin").unwrap();
+    let error = delete_duplicates_with(&duplicates, &options).unwrap_err();
+    assert!(error.to_string().contains("already exists"), "{error}");
+    assert!(copy.exists());
+    assert_eq!(fs::read(&journal_path).unwrap(), first);
+}
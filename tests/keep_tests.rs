@@ -0,0 +1,59 @@
+use intelligent_file_deduplicator::keep::{KeepPolicy, KeepRule};
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+use tempfile::tempdir;
+
+#[test]
+fn test_parse_keep_policy() {
+    let policy: KeepPolicy = "prefer:/mnt/photos, oldest,shortest".parse().unwrap();
+    assert_eq!(
+        policy.rules,
+        vec![
+            KeepRule::Prefer("/mnt/photos".into()),
+            KeepRule::Oldest,
+            KeepRule::Shortest,
+        ]
+    );
+    assert_eq!(policy.to_string(), "prefer:/mnt/photos,oldest,shortest");
+    assert!("biggest".parse::<KeepPolicy>().is_err());
+}
+
+#[test]
+fn test_keep_rules_with_tie_breakers() {
+    let dir = tempdir().unwrap();
+    let archive = dir.path().join("archive");
+    fs::create_dir(&archive).unwrap();
+
+    let now = SystemTime::now();
+    let old = dir.path().join("old.txt");
+    let new = dir.path().join("new.txt");
+    let archived = archive.join("kept-longer-name.txt");
+    for (path, age) in [(&old, 300), (&new, 0), (&archived, 300)] {
+        File::create(path)
+            .unwrap()
+            .set_modified(now - Duration::from_secs(age))
+            .unwrap();
+    }
+
+    let files: Vec<PathBuf> = [&old, &new, &archived]
+        .iter()
+        .map(|p| p.to_path_buf())
+        .collect();
+    let all = [0, 1, 2];
+    let choose = |policy: &str| {
+        let policy: KeepPolicy = policy.parse().unwrap();
+        policy.choose(&files, &all).unwrap()
+    };
+
+    assert_eq!(choose("newest"), 1);
+    // Two files share the oldest time; the shorter path breaks the tie
+    assert_eq!(choose("oldest,shortest"), 0);
+    assert_eq!(choose("oldest,longest"), 2);
+    assert_eq!(choose(&format!("prefer:{},newest", archive.display())), 2);
+    assert_eq!(choose("alpha"), 2);
+    // Default: the first file listed
+    assert_eq!(choose(""), 0);
+}
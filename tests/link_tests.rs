@@ -0,0 +1,181 @@
+#![cfg(unix)]
+
+use intelligent_file_deduplicator::hashing::hash_file;
+use intelligent_file_deduplicator::journal::{Journal, JournalAction, restore_journal};
+use intelligent_file_deduplicator::link::{metadata_differences, same_file};
+use intelligent_file_deduplicator::safe_delete::{
+    DeleteMode, DeleteOptions, delete_duplicates_with,
+};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    os::unix::fs::PermissionsExt,
+    path::Path,
+};
+use tempfile::tempdir;
+
+#[test]
+fn test_link_replaces_copy_and_restore_splits_it() {
+    let dir = tempdir().unwrap();
+    let keep = dir.path().join("a.txt");
+    let copy = dir.path().join("sub").join("b.txt");
+    fs::create_dir_all(copy.parent().unwrap()).unwrap();
+    for file in [&keep, &copy] {
+        File::create(file).unwrap().write_all(b"twin").unwrap();
+    }
+    fs::set_permissions(&copy, fs::Permissions::from_mode(0o600)).unwrap();
+    fs::set_permissions(&keep, fs::Permissions::from_mode(0o644)).unwrap();
+
+    let mut duplicates = HashMap::new();
+    duplicates.insert(
+        hash_file(keep.to_str().unwrap()).unwrap(),
+        vec![keep.clone(), copy.clone()],
+    );
+    let differences =
+        metadata_differences(&fs::metadata(&copy).unwrap(), &fs::metadata(&keep).unwrap());
+    assert_eq!(differences, vec!["mode 0600 -> 0644".to_string()]);
+
+    // A dry run leaves the copy alone
+    let journal_path = dir.path().join("journal.jsonl");
+    let mut options = DeleteOptions {
+        dry_run: true,
+        mode: DeleteMode::Hardlink,
+        journal: Some(journal_path.clone()),
+        ..DeleteOptions::default()
+    };
+    delete_duplicates_with(&duplicates, &options).unwrap();
+    assert!(!same_file(&keep, &copy).unwrap());
+
+    options.dry_run = false;
+    delete_duplicates_with(&duplicates, &options).unwrap();
+    assert!(same_file(&keep, &copy).unwrap());
+    assert_eq!(fs::read(&copy).unwrap(), b"twin");
+    // No temporary files are left behind
+    assert_eq!(fs::read_dir(copy.parent().unwrap()).unwrap().count(), 1);
+
+    let journal = Journal::read(&journal_path).unwrap();
+    assert_eq!(journal.entries.len(), 1);
+    assert_eq!(journal.entries[0].action, JournalAction::Hardlinked);
+
+    let outcomes = restore_journal(&journal);
+    assert!(outcomes.iter().all(|outcome| outcome.result.is_ok()));
+    assert!(!same_file(&keep, &copy).unwrap());
+    assert_eq!(fs::read(&copy).unwrap(), b"twin");
+    let mode = fs::metadata(&copy).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode, 0o600);
+}
+
+#[test]
+fn test_reflink_keeps_files_independent_or_skips() {
+    use intelligent_file_deduplicator::reflink::reflink_file;
+    use std::{io::ErrorKind, os::unix::fs::MetadataExt};
+
+    let dir = tempdir().unwrap();
+    let keep = dir.path().join("a.bin");
+    let copy = dir.path().join("b.bin");
+    let data = vec![7u8; 64 * 1024];
+    for file in [&keep, &copy] {
+        File::create(file).unwrap().write_all(&data).unwrap();
+    }
+    fs::set_permissions(&copy, fs::Permissions::from_mode(0o600)).unwrap();
+    let inode = fs::metadata(&copy).unwrap().ino();
+
+    // Supported on Btrfs/XFS; ext4 and tmpfs must report Unsupported and change nothing
+    match reflink_file(&copy, &keep) {
+        Ok(_) => assert!(!same_file(&keep, &copy).unwrap()),
+        Err(e) => {
+            assert_eq!(e.kind(), ErrorKind::Unsupported);
+            assert_eq!(fs::metadata(&copy).unwrap().ino(), inode);
+        }
+    }
+    assert_eq!(fs::read(&copy).unwrap(), data);
+    let mode = fs::metadata(&copy).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode, 0o600);
+
+    // Differing contents are never cloned
+    File::create(&copy)
+        .unwrap()
+        .write_all(&[8u8; 64 * 1024])
+        .unwrap();
+    assert!(reflink_file(&copy, &keep).is_err());
+    assert_eq!(fs::read(&copy).unwrap(), vec![8u8; 64 * 1024]);
+}
+
+#[test]
+fn test_relative_symlinks_across_sibling_dirs() {
+    let dir = tempdir().unwrap();
+    let keep = dir.path().join("originals").join("a.txt");
+    let copy = dir.path().join("cache").join("deep").join("b.txt");
+    for file in [&keep, &copy] {
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        File::create(file).unwrap().write_all(b"shared").unwrap();
+    }
+    let mut duplicates = HashMap::new();
+    duplicates.insert(
+        hash_file(keep.to_str().unwrap()).unwrap(),
+        vec![keep.clone(), copy.clone()],
+    );
+
+    let journal_path = dir.path().join("journal.jsonl");
+    let options = DeleteOptions {
+        mode: DeleteMode::Symlink { relative: true },
+        journal: Some(journal_path.clone()),
+        ..DeleteOptions::default()
+    };
+    delete_duplicates_with(&duplicates, &options).unwrap();
+
+    assert!(copy.symlink_metadata().unwrap().file_type().is_symlink());
+    assert_eq!(
+        fs::read_link(&copy).unwrap(),
+        Path::new("../../originals/a.txt")
+    );
+    assert_eq!(fs::read(&copy).unwrap(), b"shared");
+
+    let journal = Journal::read(&journal_path).unwrap();
+    assert_eq!(journal.entries[0].action, JournalAction::Symlinked);
+    let outcomes = restore_journal(&journal);
+    assert!(outcomes.iter().all(|outcome| outcome.result.is_ok()));
+    assert!(copy.symlink_metadata().unwrap().file_type().is_file());
+    assert_eq!(fs::read(&copy).unwrap(), b"shared");
+}
+
+#[test]
+fn test_followed_symlinks_are_never_linked_or_kept() {
+    use intelligent_file_deduplicator::filter::FilterOptions;
+    use intelligent_file_deduplicator::scanner::{ScanOptions, SymlinkPolicy, scan_directory};
+
+    let dir = tempdir().unwrap();
+    let tree = dir.path().join("tree");
+    fs::create_dir(&tree).unwrap();
+    let outside = dir.path().join("outside.txt");
+    fs::write(&outside, "shared").unwrap();
+    std::os::unix::fs::symlink(&outside, tree.join("alias.txt")).unwrap();
+    fs::write(tree.join("copy.txt"), "shared").unwrap();
+    fs::write(tree.join("other.txt"), "shared").unwrap();
+
+    let scan = scan_directory(
+        &tree,
+        &FilterOptions::default(),
+        &ScanOptions {
+            symlinks: SymlinkPolicy::Follow,
+            ..ScanOptions::default()
+        },
+    );
+    let group = scan.duplicates.values().next().unwrap();
+    assert_eq!(group.len(), 3);
+    // The link frees nothing; one of the two real copies must stay
+    assert_eq!(scan.stats.reclaimable_bytes, "shared".len() as u64);
+
+    let options = DeleteOptions {
+        mode: DeleteMode::Hardlink,
+        ..DeleteOptions::default()
+    };
+    let report = delete_duplicates_with(&scan.duplicates, &options).unwrap();
+    assert!(report.is_complete());
+    assert_ne!(report.outcomes[0].kept, tree.join("alias.txt"));
+    let alias = fs::symlink_metadata(tree.join("alias.txt")).unwrap();
+    assert!(alias.file_type().is_symlink());
+    assert!(same_file(&tree.join("copy.txt"), &tree.join("other.txt")).unwrap());
+    assert!(!same_file(&tree.join("copy.txt"), &outside).unwrap());
+}
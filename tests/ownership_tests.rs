@@ -0,0 +1,96 @@
+#![cfg(unix)]
+
+use intelligent_file_deduplicator::filter::FilterOptions;
+use intelligent_file_deduplicator::ownership::{GroupId, PermMask, UserId, check_deletable};
+use intelligent_file_deduplicator::safe_delete::{FileStatus, delete_duplicates};
+use std::{
+    collections::HashMap,
+    fs,
+    os::unix::fs::{MetadataExt, PermissionsExt},
+};
+use tempfile::tempdir;
+
+#[test]
+fn test_parse_owners_and_permission_masks() {
+    assert_eq!("1000".parse::<UserId>(), Ok(UserId(1000)));
+    assert_eq!("root".parse::<UserId>(), Ok(UserId(0)));
+    assert_eq!("root".parse::<GroupId>(), Ok(GroupId(0)));
+    assert!("no-such-user-here".parse::<UserId>().is_err());
+
+    assert_eq!("644".parse::<PermMask>(), Ok(PermMask::Exact(0o644)));
+    assert_eq!("-022".parse::<PermMask>(), Ok(PermMask::All(0o022)));
+    assert_eq!("/111".parse::<PermMask>(), Ok(PermMask::Any(0o111)));
+    for invalid in ["", "-", "888", "u+x", "17777", "+644"] {
+        assert!(invalid.parse::<PermMask>().is_err(), "'{invalid}'");
+    }
+
+    assert!(PermMask::Exact(0o644).matches(0o100644));
+    assert!(!PermMask::Exact(0o644).matches(0o100664));
+    assert!(PermMask::All(0o022).matches(0o666));
+    assert!(!PermMask::All(0o022).matches(0o644));
+    assert!(PermMask::Any(0o111).matches(0o744));
+    assert!(!PermMask::Any(0o111).matches(0o644));
+}
+
+#[test]
+fn test_owner_group_and_perm_filters() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("shared.txt");
+    fs::write(&file, "data").unwrap();
+    fs::set_permissions(&file, fs::Permissions::from_mode(0o664)).unwrap();
+    let metadata = fs::metadata(&file).unwrap();
+
+    let mine = FilterOptions {
+        owner: Some(metadata.uid()),
+        group: Some(metadata.gid()),
+        perm: Some(PermMask::All(0o020)),
+        ..FilterOptions::default()
+    };
+    assert!(mine.matches(&file));
+
+    let someone_else = FilterOptions {
+        owner: Some(metadata.uid() + 1),
+        ..FilterOptions::default()
+    };
+    assert!(!someone_else.matches(&file));
+
+    let private = FilterOptions {
+        perm: Some(PermMask::Exact(0o600)),
+        ..FilterOptions::default()
+    };
+    assert!(!private.matches(&file));
+}
+
+#[test]
+fn test_undeletable_files_are_skipped_and_kept() {
+    // Root may write to any directory, so there is nothing to refuse
+    if unsafe { libc::geteuid() } == 0 {
+        return;
+    }
+    let dir = tempdir().unwrap();
+    let locked = dir.path().join("locked");
+    fs::create_dir(&locked).unwrap();
+    let (a, b, c) = (
+        dir.path().join("a.txt"),
+        locked.join("b.txt"),
+        locked.join("c.txt"),
+    );
+    for path in [&a, &b, &c] {
+        fs::write(path, "same").unwrap();
+    }
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o555)).unwrap();
+    assert!(check_deletable(&a).is_ok());
+    assert!(check_deletable(&b).is_err());
+
+    let mut duplicates = HashMap::new();
+    duplicates.insert("hash".to_string(), vec![a.clone(), b.clone(), c.clone()]);
+    let report = delete_duplicates(&duplicates, false).unwrap();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+    // A locked file survives, the other is reported, and the free one goes
+    assert!(report.outcomes.iter().all(|outcome| outcome.kept == b));
+    assert!(!a.exists());
+    let skipped = report.outcomes.iter().find(|o| o.path == c).unwrap();
+    assert!(matches!(skipped.status, FileStatus::Skipped(_)));
+    assert!(c.exists());
+}
@@ -1,6 +1,9 @@
-use intelligent_file_deduplicator::report::write_json_report;
+use intelligent_file_deduplicator::hashing::HashAlgorithm;
+use intelligent_file_deduplicator::report::{write_json_report, write_json_report_with};
+use intelligent_file_deduplicator::size::SizeFormat;
 use std::collections::HashMap;
 use std::fs;
+use std::path::PathBuf;
 use tempfile::tempdir;
 
 #[test]
@@ -9,18 +12,98 @@ fn test_json_report_generation() {
     duplicates.insert(
         "dummyhash123".to_string(),
         vec![
-            "path/to/file1.txt".to_string(),
-            "path/to/file2.txt".to_string(),
+            PathBuf::from("path/to/file1.txt"),
+            PathBuf::from("path/to/file2.txt"),
         ],
     );
 
     let temp = tempdir().unwrap();
     let report_path = temp.path().join("report.json");
 
-    write_json_report(&duplicates, &report_path).expect("Failed to write report");
+    write_json_report(&duplicates, HashAlgorithm::Sha256, &report_path)
+        .expect("Failed to write report");
 
     let contents = fs::read_to_string(&report_path).expect("Failed to read report");
     assert!(contents.contains("dummyhash123"));
+    assert!(contents.contains("\"algorithm\": \"sha256\""));
     assert!(contents.contains("file1.txt"));
     assert!(contents.contains("file2.txt"));
 }
+
+#[cfg(unix)]
+#[test]
+fn test_json_report_keeps_non_utf8_names() {
+    use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+    // "café.txt" in Latin-1
+    let latin1 = PathBuf::from(OsString::from_vec(b"caf\xe9.txt".to_vec()));
+    let mut duplicates = HashMap::new();
+    duplicates.insert("hash".to_string(), vec![PathBuf::from("plain.txt"), latin1]);
+
+    let temp = tempdir().unwrap();
+    let report_path = temp.path().join("report.json");
+    write_json_report(&duplicates, HashAlgorithm::Sha256, &report_path).unwrap();
+
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&report_path).unwrap()).unwrap();
+    let files = &report["duplicates"][0]["files"];
+    assert_eq!(files[0], "plain.txt");
+    assert_eq!(files[1]["hex"], "636166e92e747874");
+    assert_eq!(files[1]["lossy"], "caf\u{fffd}.txt");
+}
+
+#[test]
+fn test_json_report_sizes() {
+    let temp = tempdir().unwrap();
+    let (a, b) = (temp.path().join("a.bin"), temp.path().join("b.bin"));
+    fs::write(&a, vec![0u8; 1536]).unwrap();
+    fs::write(&b, vec![0u8; 1536]).unwrap();
+    let mut duplicates = HashMap::new();
+    duplicates.insert("hash".to_string(), vec![a, b]);
+
+    let read = |path: &std::path::Path| -> serde_json::Value {
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+    };
+    let human = temp.path().join("human.json");
+    write_json_report(&duplicates, HashAlgorithm::Sha256, &human).unwrap();
+    let group = &read(&human)["duplicates"][0];
+    assert_eq!(group["size"], 1536);
+    assert_eq!(group["size_human"], "1.5 KiB");
+
+    let bytes = temp.path().join("bytes.json");
+    write_json_report_with(
+        &duplicates,
+        HashAlgorithm::Sha256,
+        SizeFormat::Bytes,
+        &bytes,
+    )
+    .unwrap();
+    let group = &read(&bytes)["duplicates"][0];
+    assert_eq!(group["size"], 1536);
+    assert!(group.get("size_human").is_none());
+}
+
+#[test]
+fn test_json_report_records_detected_mime_type() {
+    let temp = tempdir().unwrap();
+    let (a, b) = (temp.path().join("a.dat"), temp.path().join("b.dat"));
+    fs::write(&a, b"%PDF-1.7 body").unwrap();
+    fs::write(&b, b"%PDF-1.7 body").unwrap();
+    let (c, d) = (temp.path().join("c.txt"), temp.path().join("d.txt"));
+    fs::write(&c, "plain").unwrap();
+    fs::write(&d, "plain").unwrap();
+    let mut duplicates = HashMap::new();
+    duplicates.insert("pdf".to_string(), vec![a, b]);
+    duplicates.insert("text".to_string(), vec![c, d]);
+
+    let output = temp.path().join("report.json");
+    write_json_report(&duplicates, HashAlgorithm::Sha256, &output).unwrap();
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+    for group in report["duplicates"].as_array().unwrap() {
+        match group["hash"].as_str().unwrap() {
+            "pdf" => assert_eq!(group["mime"], "application/pdf"),
+            _ => assert!(group.get("mime").is_none()),
+        }
+    }
+}
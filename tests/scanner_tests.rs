@@ -1,5 +1,8 @@
+use intelligent_file_deduplicator::error::DedupError;
 use intelligent_file_deduplicator::filter::FilterOptions;
-use intelligent_file_deduplicator::scanner::scan_directory_for_duplicates;
+use intelligent_file_deduplicator::scanner::{
+    ScanOptions, scan_directory, scan_directory_for_duplicates, scan_roots,
+};
 
 use std::{fs::File, io::Write, path::PathBuf};
 use tempfile::tempdir;
@@ -24,7 +27,7 @@ fn test_duplicate_detection() {
         scan_directory_for_duplicates(dir.path().to_str().unwrap(), &FilterOptions::default());
 
     let mut found = false;
-    for (_hash, files) in &duplicates {
+    for files in duplicates.values() {
         if files.len() > 1 {
             found = true;
             let file_names: Vec<_> = files
@@ -45,3 +48,319 @@ fn test_duplicate_detection() {
 
     assert!(found, "Should detect duplicates between file1 and file2");
 }
+
+#[test]
+fn test_pipeline_prunes_by_size_and_sample() {
+    let dir = tempdir().unwrap();
+
+    // Unique size: dropped at the size stage
+    File::create(dir.path().join("unique.bin"))
+        .unwrap()
+        .write_all(b"only one of these")
+        .unwrap();
+
+    // Same size, differing tails: dropped at the sample stage
+    let mut head = vec![b'A'; 20_000];
+    File::create(dir.path().join("tail_a.bin"))
+        .unwrap()
+        .write_all(&head)
+        .unwrap();
+    head[19_999] = b'B';
+    File::create(dir.path().join("tail_b.bin"))
+        .unwrap()
+        .write_all(&head)
+        .unwrap();
+
+    // Real duplicates: confirmed by the full hash
+    let mut middle = vec![b'C'; 20_000];
+    middle[10_000] = b'D';
+    for name in ["dup1.bin", "dup2.bin"] {
+        File::create(dir.path().join(name))
+            .unwrap()
+            .write_all(&middle)
+            .unwrap();
+    }
+
+    let result = scan_directory(
+        dir.path().to_str().unwrap(),
+        &FilterOptions::default(),
+        &ScanOptions::default(),
+    );
+
+    assert_eq!(result.stats.files_scanned, 5);
+    assert_eq!(result.stats.size_candidates, 4);
+    assert_eq!(result.stats.sample_candidates, 2);
+    assert_eq!(result.stats.duplicate_files, 2);
+    assert_eq!(result.stats.duplicate_groups, 1);
+    assert_eq!(result.duplicates.len(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_hardlinks_are_collapsed() {
+    let dir = tempdir().unwrap();
+    let original = dir.path().join("original.txt");
+    let link = dir.path().join("link.txt");
+    let copy = dir.path().join("copy.txt");
+
+    File::create(&original)
+        .unwrap()
+        .write_all(b"shared inode")
+        .unwrap();
+    std::fs::hard_link(&original, &link).unwrap();
+
+    // Only hardlinks: nothing to report as duplicate
+    let result = scan_directory(
+        dir.path().to_str().unwrap(),
+        &FilterOptions::default(),
+        &ScanOptions::default(),
+    );
+    assert!(result.duplicates.is_empty());
+    assert_eq!(result.hardlinks.len(), 1);
+    assert_eq!(result.stats.hardlinked_files, 1);
+    assert_eq!(result.stats.reclaimable_bytes, 0);
+
+    // A real copy is a duplicate of the inode, counted once
+    std::fs::copy(&original, &copy).unwrap();
+    let result = scan_directory(
+        dir.path().to_str().unwrap(),
+        &FilterOptions::default(),
+        &ScanOptions::default(),
+    );
+    let group = result.duplicates.values().next().unwrap();
+    assert_eq!(group.len(), 2);
+    assert_eq!(result.stats.reclaimable_bytes, "shared inode".len() as u64);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlink_policy_and_loop_detection() {
+    use intelligent_file_deduplicator::scanner::SymlinkPolicy;
+    use std::os::unix::fs::symlink;
+
+    let dir = tempdir().unwrap();
+    let sub = dir.path().join("sub");
+    std::fs::create_dir(&sub).unwrap();
+    let target = sub.join("real.txt");
+    File::create(&target)
+        .unwrap()
+        .write_all(b"real data")
+        .unwrap();
+    symlink(&target, dir.path().join("alias.txt")).unwrap();
+    // A loop back to the scan root
+    symlink(dir.path(), sub.join("loop")).unwrap();
+
+    let root = dir.path().to_str().unwrap();
+
+    let not_followed = scan_directory(root, &FilterOptions::default(), &ScanOptions::default());
+    assert!(not_followed.duplicates.is_empty());
+    assert_eq!(not_followed.stats.files_scanned, 1);
+    assert_eq!(not_followed.symlinks.len(), 2);
+    let alias = &not_followed.symlinks[0];
+    assert!(alias.link.ends_with("alias.txt"));
+    assert_eq!(
+        alias.target.as_deref(),
+        Some(std::fs::canonicalize(&target).unwrap().as_path())
+    );
+
+    // Following terminates despite the loop; the alias adds nothing to its target
+    let follow = ScanOptions {
+        symlinks: SymlinkPolicy::Follow,
+        ..ScanOptions::default()
+    };
+    let followed = scan_directory(root, &FilterOptions::default(), &follow);
+    assert!(followed.duplicates.is_empty());
+    assert!(followed.symlinks.is_empty());
+    assert_eq!(followed.stats.files_scanned, 2);
+    assert_eq!(followed.stats.hardlinked_files, 0);
+    assert!(followed.hardlinks.is_empty());
+
+    // With a real copy, the group holds the target, never the link to it
+    std::fs::copy(&target, dir.path().join("copy.txt")).unwrap();
+    let followed = scan_directory(root, &FilterOptions::default(), &follow);
+    let group = followed.duplicates.values().next().unwrap();
+    assert_eq!(group.len(), 2);
+    assert!(!group.iter().any(|path| path.ends_with("alias.txt")));
+    assert_eq!(followed.stats.reclaimable_bytes, "real data".len() as u64);
+}
+
+#[test]
+fn test_scan_collects_per_path_errors() {
+    let dir = tempdir().unwrap();
+    let missing = dir.path().join("missing");
+
+    let result = scan_directory(
+        missing.to_str().unwrap(),
+        &FilterOptions::default(),
+        &ScanOptions::default(),
+    );
+    assert!(result.duplicates.is_empty());
+    assert_eq!(result.errors.len(), 1);
+    assert!(matches!(
+        &result.errors[0],
+        DedupError::Vanished { path } if *path == missing
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_excluded_directories_are_pruned() {
+    use std::os::unix::fs::symlink;
+
+    let dir = tempdir().unwrap();
+    let modules = dir.path().join("web").join("node_modules");
+    std::fs::create_dir_all(&modules).unwrap();
+    for path in [
+        modules.join("a.js"),
+        modules.join("b.js"),
+        dir.path().join("one.tmp"),
+        dir.path().join("two.tmp"),
+    ] {
+        std::fs::write(path, "same").unwrap();
+    }
+    // Only listed if the walk enters node_modules
+    symlink(modules.join("a.js"), modules.join("link.js")).unwrap();
+
+    let root = dir.path().to_str().unwrap();
+    let unfiltered = scan_directory(root, &FilterOptions::default(), &ScanOptions::default());
+    assert_eq!(unfiltered.stats.duplicate_files, 4);
+    assert_eq!(unfiltered.symlinks.len(), 1);
+
+    let filters = FilterOptions {
+        exclude: vec!["*.tmp".parse().unwrap()],
+        exclude_dirs: vec!["node_modules".to_string()],
+        ..FilterOptions::default()
+    };
+    let result = scan_directory(root, &filters, &ScanOptions::default());
+    assert!(result.duplicates.is_empty());
+    assert_eq!(result.stats.files_scanned, 0);
+    assert!(result.symlinks.is_empty());
+}
+
+#[test]
+fn test_exclusions_ignore_directories_above_the_root() {
+    let dir = tempdir().unwrap();
+    let project = dir.path().join("build").join("proj");
+    for sub in ["src", "build", "cache/build"] {
+        std::fs::create_dir_all(project.join(sub)).unwrap();
+    }
+    for file in ["src/a.txt", "src/b.txt", "build/a.txt", "cache/build/a.txt"] {
+        std::fs::write(project.join(file), "same").unwrap();
+    }
+
+    let root = project.to_str().unwrap();
+    for filters in [
+        FilterOptions {
+            exclude: vec!["**/build/*".parse().unwrap()],
+            ..FilterOptions::default()
+        },
+        FilterOptions {
+            exclude_dirs: vec!["build".to_string()],
+            ..FilterOptions::default()
+        },
+    ] {
+        let result = scan_directory(root, &filters, &ScanOptions::default());
+        assert_eq!(result.stats.files_scanned, 2);
+        assert_eq!(result.stats.duplicate_files, 2);
+    }
+}
+
+#[test]
+fn test_ignore_files_are_respected_at_every_level() {
+    use std::fs;
+
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    for sub in ["build", "src/vendor", "logs"] {
+        fs::create_dir_all(root.join(sub)).unwrap();
+    }
+    fs::write(root.join(".gitignore"), "build/\n*.log\n").unwrap();
+    fs::write(root.join("logs/.ignore"), "!keep.log\n").unwrap();
+    fs::write(root.join("src/.hashlaserignore"), "vendor\n").unwrap();
+    for file in [
+        "a.txt",
+        "build/a.txt",
+        "src/a.txt",
+        "src/vendor/a.txt",
+        "debug.log",
+        "logs/drop.log",
+        "logs/keep.log",
+    ] {
+        fs::write(root.join(file), "same").unwrap();
+    }
+
+    let options = ScanOptions {
+        respect_ignore: true,
+        ..ScanOptions::default()
+    };
+    let result = scan_directory(root, &FilterOptions::default(), &options);
+    assert!(result.errors.is_empty());
+    let mut found: Vec<PathBuf> = result
+        .duplicates
+        .into_values()
+        .flatten()
+        .map(|path| path.strip_prefix(root).unwrap().to_path_buf())
+        .collect();
+    found.sort();
+    assert_eq!(
+        found,
+        [
+            PathBuf::from("a.txt"),
+            PathBuf::from("logs/keep.log"),
+            PathBuf::from("src/a.txt"),
+        ]
+    );
+
+    // Without the option every copy is found
+    let all = scan_directory(root, &FilterOptions::default(), &ScanOptions::default());
+    assert_eq!(all.stats.duplicate_files, 7);
+}
+
+#[test]
+fn test_multiple_roots_and_cross_root_groups() {
+    use std::fs;
+
+    let dir = tempdir().unwrap();
+    let roots: Vec<PathBuf> = ["photos", "backup", "shared"]
+        .iter()
+        .map(|name| dir.path().join(name))
+        .collect();
+    for root in &roots {
+        fs::create_dir(root).unwrap();
+    }
+    let (photos, backup, shared) = (&roots[0], &roots[1], &roots[2]);
+    fs::write(photos.join("a.jpg"), "in photos and backup").unwrap();
+    fs::write(backup.join("a.jpg"), "in photos and backup").unwrap();
+    fs::write(backup.join("b.jpg"), "in backup and shared").unwrap();
+    fs::write(shared.join("b.jpg"), "in backup and shared").unwrap();
+    fs::write(shared.join("c.txt"), "only shared").unwrap();
+    fs::write(shared.join("d.txt"), "only shared").unwrap();
+
+    let filters = FilterOptions::default();
+    let all = scan_roots(&roots, &filters, &ScanOptions::default());
+    assert_eq!(all.stats.duplicate_groups, 3);
+    assert_eq!(all.root_of(&shared.join("c.txt")), Some(2));
+    let spans: Vec<usize> = all
+        .duplicates
+        .values()
+        .map(|files| all.roots_of(files).len())
+        .collect();
+    assert_eq!(spans.iter().filter(|&&span| span == 2).count(), 2);
+
+    let options = ScanOptions {
+        cross_root: true,
+        ..ScanOptions::default()
+    };
+    let cross = scan_roots(&roots, &filters, &options);
+    assert_eq!(cross.stats.duplicate_groups, 2);
+    assert_eq!(cross.stats.duplicate_files, 4);
+
+    let options = ScanOptions {
+        present_in: vec![photos.clone(), backup.clone()],
+        ..ScanOptions::default()
+    };
+    let both = scan_roots(&roots, &filters, &options);
+    let groups: Vec<&Vec<PathBuf>> = both.duplicates.values().collect();
+    assert_eq!(groups, [&vec![backup.join("a.jpg"), photos.join("a.jpg")]]);
+    assert_eq!(both.stats.reclaimable_bytes, 20);
+}
@@ -0,0 +1,43 @@
+use intelligent_file_deduplicator::size::{ByteSize, SizeFormat};
+
+#[test]
+fn test_parse_si_and_iec_units() {
+    let parse = |s: &str| s.parse::<ByteSize>().map(|size| size.0);
+    assert_eq!(parse("1234"), Ok(1234));
+    assert_eq!(parse("12B"), Ok(12));
+    assert_eq!(parse("500k"), Ok(500_000));
+    assert_eq!(parse("500KB"), Ok(500_000));
+    assert_eq!(parse("2G"), Ok(2_000_000_000));
+    assert_eq!(parse("10MiB"), Ok(10 * 1024 * 1024));
+    assert_eq!(parse("10mi"), Ok(10 * 1024 * 1024));
+    assert_eq!(parse("1.5 GiB"), Ok(3 * 512 * 1024 * 1024));
+    assert_eq!(parse(".5k"), Ok(500));
+}
+
+#[test]
+fn test_parse_rejects_invalid_sizes() {
+    for invalid in [
+        "",
+        "abc",
+        "-5",
+        "10 XB",
+        "1.2.3M",
+        ".",
+        "1.5",
+        "99999999999P",
+    ] {
+        assert!(
+            invalid.parse::<ByteSize>().is_err(),
+            "'{invalid}' should be rejected"
+        );
+    }
+}
+
+#[test]
+fn test_format_sizes() {
+    assert_eq!(ByteSize(512).to_string(), "512 B");
+    assert_eq!(ByteSize(1536).to_string(), "1.5 KiB");
+    assert_eq!(ByteSize(10 * 1024 * 1024).to_string(), "10.0 MiB");
+    assert_eq!(SizeFormat::Bytes.format(1536), "1536 bytes");
+    assert_eq!(SizeFormat::Human.format(1536), "1.5 KiB");
+}
@@ -0,0 +1,46 @@
+use chrono::{Local, TimeZone};
+use intelligent_file_deduplicator::timestamp::Timestamp;
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn test_relative_durations() {
+    let parsed: Timestamp = "30d".parse().unwrap();
+    let expected = SystemTime::now() - Duration::from_secs(30 * 86_400);
+    let drift = expected.duration_since(parsed.0).unwrap_or_default();
+    assert!(drift < Duration::from_secs(5));
+
+    let parsed: Timestamp = "6h".parse().unwrap();
+    assert!(parsed.0 < SystemTime::now() - Duration::from_secs(6 * 3600 - 5));
+}
+
+#[test]
+fn test_absolute_dates() {
+    let midnight = Local.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap();
+    assert_eq!(
+        "2024-01-31".parse::<Timestamp>().unwrap().0,
+        SystemTime::from(midnight)
+    );
+    let afternoon = Local.with_ymd_and_hms(2024, 1, 31, 14, 30, 0).unwrap();
+    assert_eq!(
+        "2024-01-31 14:30".parse::<Timestamp>().unwrap().0,
+        SystemTime::from(afternoon)
+    );
+    assert_eq!(
+        "2024-01-31T14:30:00".parse::<Timestamp>().unwrap().0,
+        SystemTime::from(afternoon)
+    );
+    assert_eq!(
+        "1970-01-01T00:01:00Z".parse::<Timestamp>().unwrap().0,
+        SystemTime::UNIX_EPOCH + Duration::from_secs(60)
+    );
+}
+
+#[test]
+fn test_invalid_times_are_rejected() {
+    for invalid in ["", "yesterday", "30x", "2024-13-01", "2024-01-31 25:00"] {
+        assert!(
+            invalid.parse::<Timestamp>().is_err(),
+            "'{invalid}' should be rejected"
+        );
+    }
+}
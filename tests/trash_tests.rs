@@ -0,0 +1,120 @@
+use intelligent_file_deduplicator::journal::{Journal, JournalAction};
+use intelligent_file_deduplicator::safe_delete::{
+    DeleteMode, DeleteOptions, delete_duplicates_with,
+};
+use intelligent_file_deduplicator::trash::trash_file;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+};
+use tempfile::tempdir;
+
+#[test]
+fn test_quarantine_mirrors_tree_and_writes_journal() {
+    let dir = tempdir().unwrap();
+    let data = dir.path().join("data");
+    fs::create_dir_all(data.join("nested")).unwrap();
+    let keep = data.join("keep.txt");
+    let copy = data.join("nested").join("copy.txt");
+    for file in [&keep, &copy] {
+        File::create(file).unwrap().write_all(b"same").unwrap();
+    }
+
+    let mut duplicates = HashMap::new();
+    duplicates.insert("hash".to_string(), vec![keep.clone(), copy.clone()]);
+
+    let quarantine = dir.path().join("quarantine");
+    let journal_path = dir.path().join("journal.json");
+    let options = DeleteOptions {
+        mode: DeleteMode::Quarantine(quarantine.clone()),
+        journal: Some(journal_path.clone()),
+        ..DeleteOptions::default()
+    };
+    delete_duplicates_with(&duplicates, &options).unwrap();
+
+    let relative = copy.strip_prefix("/").unwrap();
+    let moved = quarantine.join(relative);
+    assert!(keep.exists());
+    assert!(!copy.exists());
+    assert_eq!(fs::read(&moved).unwrap(), b"same");
+
+    let journal = Journal::read(&journal_path).unwrap();
+    assert_eq!(journal.entries.len(), 1);
+    let entry = &journal.entries[0];
+    assert_eq!(entry.action, JournalAction::Quarantined);
+    assert_eq!(entry.original, copy);
+    assert_eq!(entry.moved_to.as_deref(), Some(moved.as_path()));
+    assert_eq!(entry.kept, keep);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_trash_follows_freedesktop_layout() {
+    let dir = tempdir().unwrap();
+    let data_home = dir.path().join("share");
+    // SAFETY: no other test in this binary reads the environment
+    unsafe { std::env::set_var("XDG_DATA_HOME", &data_home) };
+
+    let file = dir.path().join("report 1.txt");
+    File::create(&file).unwrap().write_all(b"bye").unwrap();
+
+    let trashed = trash_file(&file).unwrap();
+    assert!(!file.exists());
+    assert_eq!(trashed, data_home.join("Trash/files/report 1.txt"));
+
+    let info = fs::read_to_string(data_home.join("Trash/info/report 1.txt.trashinfo")).unwrap();
+    assert!(info.starts_with("[Trash Info]\n"));
+    assert!(info.contains("/report%201.txt\n"));
+    assert!(info.contains("DeletionDate="));
+
+    // Only the owner may list what was trashed
+    use std::os::unix::fs::PermissionsExt;
+    for trash_dir in ["Trash", "Trash/files", "Trash/info"] {
+        let mode = fs::metadata(data_home.join(trash_dir))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o700, "{trash_dir}");
+    }
+
+    // A second file with the same name gets a unique trash name
+    File::create(&file).unwrap().write_all(b"again").unwrap();
+    let second = trash_file(&file).unwrap();
+    assert_eq!(second, data_home.join("Trash/files/report 1.txt.2"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_move_across_filesystems_keeps_read_only_metadata() {
+    use intelligent_file_deduplicator::trash::move_file;
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use std::time::{Duration, SystemTime};
+
+    // Needs a second filesystem; /dev/shm is a tmpfs on most Linux systems
+    let dir = tempdir().unwrap();
+    let Ok(other) = tempfile::tempdir_in("/dev/shm") else {
+        return;
+    };
+    if fs::metadata(dir.path()).unwrap().dev() == fs::metadata(other.path()).unwrap().dev() {
+        return;
+    }
+
+    let from = dir.path().join("b.txt");
+    fs::write(&from, b"same").unwrap();
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    File::options()
+        .write(true)
+        .open(&from)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    fs::set_permissions(&from, fs::Permissions::from_mode(0o444)).unwrap();
+
+    let to = other.path().join("b.txt");
+    move_file(&from, &to).unwrap();
+    assert!(!from.exists());
+    let metadata = fs::metadata(&to).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o444);
+    assert_eq!(metadata.modified().unwrap(), modified);
+}